@@ -0,0 +1,94 @@
+//! Throughput benchmarks of ETag computation for the MD5 backends.
+//!
+//! Run `cargo bench --features openssl` to compare `md-5` with `openssl`.
+
+use std::num::{NonZeroU64, NonZeroUsize};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use s3etag::{ETagHasher, Md5Hasher, S3Etagger};
+
+const MIB: usize = 1 << 20;
+
+fn bench_backend<H: Md5Hasher>(c: &mut Criterion, backend: &str) {
+    let threshold = NonZeroU64::new(8 * MIB as u64).unwrap();
+    let chunksize = NonZeroUsize::new(8 * MIB).unwrap();
+    let data = vec![0x5a_u8; 64 * MIB];
+
+    let mut group = c.benchmark_group(format!("{}/single", backend));
+    for size in [4 << 10, MIB, 8 * MIB - 1] {
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &data[..size],
+            |b, data| b.iter(|| s3etag::etag_single::<H>(data)),
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("{}/multipart", backend));
+    group.sample_size(10);
+    for size in [8 * MIB, 64 * MIB] {
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &data[..size],
+            |b, data| b.iter(|| s3etag::etag::<H>(data, threshold, chunksize)),
+        );
+    }
+    group.finish();
+
+    // Writes of the buffer sizes, some of which cross chunk boundaries, show the cost of the
+    // boundary handling in the multipart mode compared to chunk-aligned writes.
+    let mut group = c.benchmark_group(format!("{}/buffer", backend));
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for write_size in [64 << 10, 100_000, 8 * MIB] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(write_size),
+            &write_size,
+            |b, &n| {
+                b.iter(|| {
+                    let len = data.len() as u64;
+                    let mut hasher = S3Etagger::<H>::for_known_size(len, threshold, chunksize);
+                    for chunk in data.chunks(n) {
+                        hasher.update(chunk);
+                    }
+                    hasher.finalize()
+                })
+            },
+        );
+    }
+    group.finish();
+
+    // Small writes exercise the update loop of the streaming mode, which buffers data up to the
+    // threshold before selecting the mode.
+    let mut group = c.benchmark_group(format!("{}/streaming", backend));
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for write_size in [512, 64 << 10] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(write_size),
+            &write_size,
+            |b, &n| {
+                b.iter(|| {
+                    let mut hasher = S3Etagger::<H>::new(threshold, chunksize);
+                    for chunk in data.chunks(n) {
+                        hasher.update(chunk);
+                    }
+                    hasher.finalize()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn backends(c: &mut Criterion) {
+    bench_backend::<md5::Md5>(c, "md-5");
+
+    #[cfg(feature = "openssl")]
+    bench_backend::<s3etag::OpensslMd5>(c, "openssl");
+}
+
+criterion_group!(benches, backends);
+criterion_main!(benches);
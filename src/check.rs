@@ -0,0 +1,469 @@
+//! Verification of files against ETag manifests.
+
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::{fmt, io, path, str};
+
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt as _;
+
+use s3etag::ETag;
+
+use crate::{output, Algorithm, Config, Digest};
+
+/// The parameters of ETag calculation recorded in a manifest by `--header`.
+///
+/// The header is a comment line in the form of `# s3etag VERSION threshold=N chunksize=N
+/// auto-chunksize=BOOL algorithm=NAME`, where the sizes are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub threshold: NonZeroU64,
+    pub chunksize: NonZeroUsize,
+    pub auto_chunksize: bool,
+    pub algorithm: Algorithm,
+}
+
+/// The beginning of a header line, which is followed by the version.
+const HEADER_PREFIX: &str = concat!("# ", env!("CARGO_PKG_NAME"), " ");
+
+impl Header {
+    /// Creates a header from the configuration, recording the threshold in effect.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            threshold: config.threshold(),
+            chunksize: config.chunksize,
+            auto_chunksize: config.auto_chunksize,
+            algorithm: config.algorithm,
+        }
+    }
+
+    /// Parses a header line, returning `None` if the line is not a header.
+    ///
+    /// Unknown keys are ignored for compatibility with future versions.
+    fn parse(line: &[u8]) -> Option<Result<Self, String>> {
+        let rest = line.strip_prefix(HEADER_PREFIX.as_bytes())?;
+        Some(Self::parse_fields(rest))
+    }
+
+    fn parse_fields(fields: &[u8]) -> Result<Self, String> {
+        let fields = str::from_utf8(fields).map_err(|_| "invalid UTF-8".to_owned())?;
+        let (mut threshold, mut chunksize, mut auto_chunksize, mut algorithm) =
+            (None, None, false, None);
+        // The first field is the version of the tool that wrote the header.
+        for field in fields.split_ascii_whitespace().skip(1) {
+            let Some((key, value)) = field.split_once('=') else {
+                return Err(format!("invalid field `{}`", field));
+            };
+            let invalid = || format!("invalid {} `{}`", key, value);
+            match key {
+                "threshold" => threshold = Some(value.parse().map_err(|_| invalid())?),
+                "chunksize" => chunksize = Some(value.parse().map_err(|_| invalid())?),
+                "auto-chunksize" => auto_chunksize = value.parse().map_err(|_| invalid())?,
+                "algorithm" => algorithm = Some(Algorithm::parse(value)?),
+                _ => (),
+            }
+        }
+        Ok(Self {
+            threshold: threshold.ok_or("missing threshold")?,
+            chunksize: chunksize.ok_or("missing chunksize")?,
+            auto_chunksize,
+            algorithm: algorithm.ok_or("missing algorithm")?,
+        })
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{} threshold={} chunksize={} auto-chunksize={} algorithm={}",
+            HEADER_PREFIX,
+            env!("CARGO_PKG_VERSION"),
+            self.threshold,
+            self.chunksize,
+            self.auto_chunksize,
+            self.algorithm.name()
+        )
+    }
+}
+
+/// Recomputes the ETags of the files listed in a manifest and reports whether each of them
+/// matches the expected value.
+///
+/// A manifest consists of lines in the text output format, either in the default two-column layout
+/// or in the BSD-style layout of `--tag`. A [`Header`] line replaces the threshold and chunksize
+/// settings of `config` for the following lines of the manifest, and the settings are restored at
+/// the end, whereas the other lines that start with `#` are ignored as comments. This function
+/// returns `Ok(false)` if any file does not match or cannot be read, or if any line is improperly
+/// formatted, while it returns an error if the manifest itself cannot be read or the results cannot
+/// be written.
+///
+/// The statuses are highlighted with ANSI escape sequences if `color` is set.
+pub fn check_manifest(
+    manifest: &path::Path,
+    config: &mut Config,
+    color: bool,
+    writer: &mut impl io::Write,
+    buffer: &mut [u8],
+) -> io::Result<bool> {
+    let reader: Box<dyn io::BufRead> = if manifest.as_os_str() == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(io::BufReader::new(crate::open_and_fadvise_seq(manifest)?))
+    };
+
+    let saved = (
+        config.threshold,
+        config.chunksize,
+        config.auto_chunksize,
+        config.mode,
+    );
+    let result = check_lines(reader, manifest, config, color, writer, buffer);
+    (
+        config.threshold,
+        config.chunksize,
+        config.auto_chunksize,
+        config.mode,
+    ) = saved;
+    result
+}
+
+fn check_lines(
+    reader: impl io::BufRead,
+    manifest: &path::Path,
+    config: &mut Config,
+    color: bool,
+    writer: &mut impl io::Write,
+    buffer: &mut [u8],
+) -> io::Result<bool> {
+    let mut success = true;
+    for (i, line) in reader.split(b'\n').enumerate() {
+        crate::interrupt::check()?;
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with(b"#") {
+            match Header::parse(&line) {
+                Some(Ok(header)) if header.algorithm != Algorithm::Md5 => {
+                    success = false;
+                    eprintln!(
+                        "error: {}:{}: {} checksums cannot be checked",
+                        manifest.display(),
+                        i + 1,
+                        header.algorithm.name()
+                    );
+                }
+                Some(Ok(header)) => {
+                    config.threshold = header.threshold;
+                    config.chunksize = header.chunksize;
+                    config.auto_chunksize = header.auto_chunksize;
+                    config.mode = None;
+                }
+                Some(Err(e)) => {
+                    success = false;
+                    eprintln!(
+                        "error: {}:{}: invalid header: {}",
+                        manifest.display(),
+                        i + 1,
+                        e
+                    );
+                }
+                None => (),
+            }
+            continue;
+        }
+        let Some((expected, filename)) = parse_line(&line) else {
+            success = false;
+            eprintln!(
+                "error: {}:{}: improperly formatted line",
+                manifest.display(),
+                i + 1
+            );
+            continue;
+        };
+
+        let (status, ok) = match crate::open_input(&filename)
+            .and_then(|input| crate::hash_input(input, &filename, config, buffer))
+        {
+            Ok((digest, _)) if digest == Digest::ETag(expected) => ("OK", true),
+            Ok(_) => ("FAILED", false),
+            Err(e) => {
+                eprintln!("error: {}: {}", filename.display(), e);
+                ("FAILED open or read", false)
+            }
+        };
+        success &= ok;
+        output::write_filename(writer, &filename)?;
+        if color {
+            // green for OK and red for FAILED
+            let code = if ok { 32 } else { 31 };
+            writeln!(writer, ": \x1b[{}m{}\x1b[0m", code, status)?;
+        } else {
+            writeln!(writer, ": {}", status)?;
+        }
+    }
+    Ok(success)
+}
+
+/// Parses a manifest line into the expected ETag and the filename.
+///
+/// The filename is expected to follow the ETag padded with spaces just as in the text output
+/// format, which preserves leading spaces in the filename. Any other number of spaces between the
+/// two fields is also accepted, in which case leading spaces in the filename are lost, and so is
+/// the `*` binary mode marker of `md5sum` after a single space. A single tab as printed with
+/// `--tab` also separates the fields. The base64 and size columns printed with `--both` and
+/// `--size` are skipped in the padded and tab-separated layouts. A line in the BSD-style layout is
+/// split at the last `) = ` so that the filename may contain it.
+fn parse_line(line: &[u8]) -> Option<(ETag, path::PathBuf)> {
+    let (expected, filename) = match line
+        .strip_prefix(output::ETAG_TAG.as_bytes())
+        .and_then(|rest| rest.strip_prefix(b" ("))
+    {
+        Some(rest) => {
+            let sep = rest.windows(4).rposition(|w| w == b") = ")?;
+            let expected = str::from_utf8(&rest[sep + 4..]).ok()?.parse().ok()?;
+            (expected, &rest[..sep])
+        }
+        None => {
+            let sep = line.iter().position(|&c| c == b' ' || c == b'\t')?;
+            let expected = str::from_utf8(&line[..sep]).ok()?.parse().ok()?;
+
+            let rest = &line[sep..];
+            let pad = output::ETAG_WIDTH.saturating_sub(sep) + 1;
+            let filename = match rest.get(..pad) {
+                _ if rest.starts_with(b"\t") => skip_columns(&expected, &rest[1..], true),
+                Some(spaces) if rest.len() > pad && spaces.iter().all(|&c| c == b' ') => {
+                    skip_columns(&expected, &rest[pad..], false)
+                }
+                _ if rest.starts_with(b" *") => &rest[2..],
+                _ => rest.trim_ascii_start(),
+            };
+            (expected, filename)
+        }
+    };
+    if filename.is_empty() {
+        return None;
+    }
+
+    #[cfg(unix)]
+    let filename = std::ffi::OsStr::from_bytes(filename).into();
+    #[cfg(not(unix))]
+    let filename = str::from_utf8(filename).ok()?.into();
+
+    Some((expected, filename))
+}
+
+/// Skips the base64 and size columns that precede the filename, which are separated by tabs if
+/// `tab` is set or padded with spaces to the column widths otherwise.
+///
+/// The base64 column is recognized by the base64 form of `expected` and the size column by a
+/// decimal integer filling the column, so a filename is mistaken for the size column only if it
+/// starts with such an integer followed by a separator.
+fn skip_columns<'a>(expected: &ETag, mut rest: &'a [u8], tab: bool) -> &'a [u8] {
+    let base64 = if tab {
+        format!("{}\t", expected.to_base64())
+    } else {
+        format!(
+            "{:<width$} ",
+            expected.to_base64(),
+            width = output::BASE64_WIDTH
+        )
+    };
+    rest = rest.strip_prefix(base64.as_bytes()).unwrap_or(rest);
+
+    let size_end = if tab {
+        rest.iter().position(|&c| c == b'\t')
+    } else {
+        (rest.get(output::SIZE_WIDTH) == Some(&b' ')).then_some(output::SIZE_WIDTH)
+    };
+    if let Some(end) = size_end {
+        let size = rest[..end].trim_ascii_start();
+        if !size.is_empty() && size.iter().all(u8::is_ascii_digit) && rest.len() > end + 1 {
+            rest = &rest[end + 1..];
+        }
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGLE: &str = "25f9e794323b453885f5181f1b624d0b";
+    const MULTI: &str = "f452076d11f30dfc8658282bf01f27f4-3";
+
+    fn parse(line: &str) -> Option<(String, String)> {
+        parse_line(line.as_bytes())
+            .map(|(etag, filename)| (etag.to_string(), filename.to_str().unwrap().to_owned()))
+    }
+
+    fn expected(etag: &str, filename: &str) -> Option<(String, String)> {
+        Some((etag.to_owned(), filename.to_owned()))
+    }
+
+    /// Returns a record printed in the text format with the given layout options.
+    fn print(etag: &str, filename: &str, tab: bool, both: bool, size: bool) -> String {
+        print_with_tag(etag, filename, None, tab, both, size)
+    }
+
+    fn print_with_tag(
+        etag: &str,
+        filename: &str,
+        tag: Option<&'static str>,
+        tab: bool,
+        both: bool,
+        size: bool,
+    ) -> String {
+        let options = output::Options {
+            format: output::Format::Text,
+            etag_only: false,
+            zero_terminated: false,
+            base64: false,
+            content_md5: false,
+            both,
+            tag,
+            tab,
+            size,
+            expect: None,
+            combine: false,
+        };
+        let mut buf = Vec::new();
+        let digest = Digest::ETag(etag.parse().unwrap());
+        output::Printer::new(&mut buf, options)
+            .print(path::Path::new(filename), &digest, 1234)
+            .unwrap();
+        let mut line = String::from_utf8(buf).unwrap();
+        assert_eq!(line.pop(), Some('\n'));
+        line
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = Header {
+            threshold: NonZeroU64::new(4).unwrap(),
+            chunksize: NonZeroUsize::new(5 << 20).unwrap(),
+            auto_chunksize: true,
+            algorithm: Algorithm::Md5,
+        };
+        let line = header.to_string();
+        assert!(line.starts_with("# s3etag "));
+        assert_eq!(Header::parse(line.as_bytes()), Some(Ok(header)));
+    }
+
+    #[test]
+    fn header_fields() {
+        let parse = |line: &str| Header::parse(line.as_bytes());
+        let header = parse("# s3etag 9.9.9 future=1 algorithm=MD5 chunksize=4 threshold=8");
+        assert_eq!(
+            header,
+            Some(Ok(Header {
+                threshold: NonZeroU64::new(8).unwrap(),
+                chunksize: NonZeroUsize::new(4).unwrap(),
+                auto_chunksize: false,
+                algorithm: Algorithm::Md5,
+            }))
+        );
+
+        let error = |line| parse(line).unwrap().unwrap_err();
+        assert_eq!(
+            error("# s3etag 1 chunksize=4 algorithm=md5"),
+            "missing threshold"
+        );
+        assert_eq!(
+            error("# s3etag 1 threshold=4 algorithm=md5"),
+            "missing chunksize"
+        );
+        assert_eq!(
+            error("# s3etag 1 threshold=4 chunksize=4"),
+            "missing algorithm"
+        );
+        assert_eq!(
+            error("# s3etag 1 threshold=0 chunksize=4 algorithm=md5"),
+            "invalid threshold `0`"
+        );
+        assert_eq!(
+            error("# s3etag 1 threshold=4 chunksize=4MB algorithm=md5"),
+            "invalid chunksize `4MB`"
+        );
+        assert_eq!(
+            error("# s3etag 1 threshold=4 chunksize=4 auto-chunksize algorithm=md5"),
+            "invalid field `auto-chunksize`"
+        );
+        assert!(error("# s3etag 1 threshold=4 chunksize=4 algorithm=bogus")
+            .starts_with("unknown algorithm `bogus`"));
+
+        assert_eq!(parse("# comment"), None);
+        assert_eq!(
+            parse("# s3etagx 1 threshold=4 chunksize=4 algorithm=md5"),
+            None
+        );
+    }
+
+    #[test]
+    fn printed_layouts() {
+        for etag in [SINGLE, MULTI] {
+            for filename in ["a", "  leading spaces", "1234 digits", "tab\there"] {
+                for layout in 0..8 {
+                    let (tab, both, size) = (layout & 1 != 0, layout & 2 != 0, layout & 4 != 0);
+                    let line = print(etag, filename, tab, both, size);
+                    assert_eq!(parse(&line), expected(etag, filename), "{:?}", line);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bsd_tag() {
+        for etag in [SINGLE, MULTI] {
+            for filename in ["a", " a ", "(a)", "a) = b", "a) = ", ") = a) = b"] {
+                let line =
+                    print_with_tag(etag, filename, Some(output::ETAG_TAG), false, false, false);
+                assert_eq!(parse(&line), expected(etag, filename), "{:?}", line);
+            }
+        }
+        let line = format!("S3ETAG (a) = {}", MULTI.to_ascii_uppercase());
+        assert_eq!(parse(&line), expected(MULTI, "a"));
+        assert_eq!(parse(&format!("S3ETAG () = {}", SINGLE)), None);
+        assert_eq!(parse(&format!("S3ETAG (a) = {}x", SINGLE)), None);
+        assert_eq!(parse(&format!("S3ETAG (a)={}", SINGLE)), None);
+        assert_eq!(parse(&format!("MD5 (a) = {}", SINGLE)), None);
+    }
+
+    #[test]
+    fn other_spacing() {
+        let line = format!("{} a", SINGLE);
+        assert_eq!(parse(&line), expected(SINGLE, "a"));
+        let line = format!("{}  a", SINGLE);
+        assert_eq!(parse(&line), expected(SINGLE, "a"));
+        let line = format!("{}   a b", MULTI);
+        assert_eq!(parse(&line), expected(MULTI, "a b"));
+        let line = format!("{}\t\ta", SINGLE);
+        assert_eq!(parse(&line), expected(SINGLE, "\ta"));
+    }
+
+    #[test]
+    fn binary_marker() {
+        let line = format!("{} *a", SINGLE);
+        assert_eq!(parse(&line), expected(SINGLE, "a"));
+        let line = format!("{} *a", MULTI);
+        assert_eq!(parse(&line), expected(MULTI, "a"));
+    }
+
+    #[test]
+    fn uppercase_and_quoted() {
+        let line = format!("{} a", SINGLE.to_ascii_uppercase());
+        assert_eq!(parse(&line), expected(SINGLE, "a"));
+        let line = format!("\"{}\" a", MULTI);
+        assert_eq!(parse(&line), expected(MULTI, "a"));
+    }
+
+    #[test]
+    fn improperly_formatted() {
+        assert_eq!(parse(SINGLE), None);
+        assert_eq!(parse(&format!("{} ", SINGLE)), None);
+        assert_eq!(parse(&format!("{}\t", SINGLE)), None);
+        assert_eq!(parse("25f9e794323b453885f5181f1b624d0 a"), None);
+        assert_eq!(parse("f452076d11f30dfc8658282bf01f27f4-0 a"), None);
+        assert_eq!(parse("not an etag"), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! Detection of the chunksize used to produce a multipart ETag.
+
+use std::{io, num::NonZeroUsize, path};
+
+use s3etag::{ETag, ETagHasher, ETagHasherMulti};
+
+use crate::{output, Md5};
+
+/// The maximum number of candidate chunksizes listed individually in the error message.
+const MAX_LISTED: usize = 16;
+
+/// Searches for a chunksize that reproduces the `target` ETag for a file and prints it.
+///
+/// This function hashes the file once with all the candidates given by
+/// [`s3etag::chunksize_candidates`] and returns an error if none of them matches.
+pub fn match_etag(
+    filename: &path::Path,
+    target: &ETag,
+    writer: &mut impl io::Write,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    let Some(part_count) = target.part_count() else {
+        return Err(io::Error::other("target ETag is not a multipart ETag"));
+    };
+    let mut file = crate::open_and_fadvise_seq(filename)?;
+    let candidates = s3etag::chunksize_candidates(file.metadata()?.len(), part_count);
+
+    let mut hashers = candidates
+        .iter()
+        .map(|&c| ETagHasherMulti::<Md5>::new(c))
+        .collect::<Vec<_>>();
+    loop {
+        match io::Read::read(&mut file, buffer) {
+            Ok(0) => break,
+            Ok(n) => hashers.iter_mut().for_each(|h| h.update(&buffer[..n])),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    match candidates
+        .iter()
+        .zip(hashers)
+        .find_map(|(&c, h)| (h.finalize() == *target).then_some(c))
+    {
+        Some(chunksize) => {
+            write!(
+                writer,
+                "{:<width$} ",
+                format_size(chunksize),
+                width = output::ETAG_WIDTH
+            )?;
+            output::write_filename(writer, filename)?;
+            writer.write_all(b"\n")
+        }
+        None if candidates.len() > MAX_LISTED => Err(io::Error::other(format!(
+            "none of the {} candidate chunksizes ({} to {}) reproduces the target ETag",
+            candidates.len(),
+            format_size(candidates[0]),
+            format_size(candidates[candidates.len() - 1])
+        ))),
+        None => Err(io::Error::other(format!(
+            "none of the {} candidate chunksizes ({}) reproduces the target ETag",
+            candidates.len(),
+            candidates
+                .iter()
+                .map(|&c| format_size(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Formats a size with the largest size suffix that represents it exactly.
+fn format_size(size: NonZeroUsize) -> String {
+    let size = size.get();
+    match ["TB", "GB", "MB", "KB"]
+        .iter()
+        .zip([40, 30, 20, 10])
+        .find(|&(_, shift)| size.trailing_zeros() >= shift)
+    {
+        Some((unit, shift)) => format!("{}{}", size >> shift, unit),
+        None => size.to_string(),
+    }
+}
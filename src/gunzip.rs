@@ -0,0 +1,55 @@
+//! Decompression of gzip input before hashing.
+
+use std::io;
+
+/// A reader adapter that decompresses gzip data, including concatenated gzip members as `gunzip`
+/// does.
+///
+/// The errors of decoding a malformed stream are reported as [`io::ErrorKind::InvalidData`] with an
+/// `invalid gzip data` message, whereas the errors of reading the underlying input are passed
+/// through as they are.
+#[derive(Debug)]
+pub struct Reader<R> {
+    decoder: flate2::read::MultiGzDecoder<Source<R>>,
+}
+
+impl<R: io::Read> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            decoder: flate2::read::MultiGzDecoder::new(Source {
+                inner,
+                failed: false,
+            }),
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf).map_err(|e| {
+            if self.decoder.get_ref().failed {
+                e
+            } else {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid gzip data: {}", e),
+                )
+            }
+        })
+    }
+}
+
+/// The underlying input, which records whether an error has come from it.
+#[derive(Debug)]
+struct Source<R> {
+    inner: R,
+    failed: bool,
+}
+
+impl<R: io::Read> io::Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.failed = result.is_err();
+        result
+    }
+}
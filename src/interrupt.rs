@@ -0,0 +1,75 @@
+//! Graceful handling of Ctrl-C.
+//!
+//! The signal handler only sets a flag and never touches the output writer, so it cannot deadlock
+//! with the thread that holds the writer lock. Instead, the read loops and the loops over FILEs
+//! poll the flag and stop, after which the records already printed are flushed through the usual
+//! path and the process exits with status 130. A second Ctrl-C terminates the process immediately
+//! without flushing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{io, process};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The conventional exit status of a process terminated by `SIGINT`.
+const EXIT_STATUS: u8 = 128 + 2;
+
+/// Installs the Ctrl-C handler, warning if it cannot be installed.
+#[cfg(feature = "ctrlc")]
+pub fn install() {
+    let result = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            process::exit(EXIT_STATUS.into());
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("warning: cannot handle Ctrl-C: {}", e);
+    }
+}
+
+/// Does nothing, leaving the default behavior of Ctrl-C that terminates the process.
+#[cfg(not(feature = "ctrlc"))]
+pub fn install() {}
+
+/// Returns `true` if Ctrl-C has been pressed.
+pub fn is_set() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Returns an error if Ctrl-C has been pressed.
+pub fn check() -> io::Result<()> {
+    if is_set() {
+        Err(io::Error::other("interrupted"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the exit status for an interrupted process, or `exit_code` otherwise.
+pub fn exit_code(exit_code: process::ExitCode) -> process::ExitCode {
+    if is_set() {
+        EXIT_STATUS.into()
+    } else {
+        exit_code
+    }
+}
+
+/// A reader adapter that fails with an error once Ctrl-C has been pressed, for the read loops
+/// outside this binary.
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: R,
+}
+
+impl<R> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: io::Read> io::Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check()?;
+        self.inner.read(buf)
+    }
+}
@@ -1,8 +1,33 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use std::{fmt, mem, num::NonZeroUsize};
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-use arrayvec::ArrayString;
+#[cfg(feature = "alloc")]
+use alloc::{
+    string::{String, ToString as _},
+    vec::Vec,
+};
+use core::num::{NonZeroU64, NonZeroUsize};
+use core::{error, fmt, hint, marker::PhantomData, mem, str};
+#[cfg(feature = "std")]
+use std::io;
+
+use arrayvec::{ArrayString, ArrayVec};
+
+/// The maximum number of parts allowed in an S3 multipart upload.
+pub const MAX_PART_COUNT: usize = 10_000;
+
+/// The default `multipart_threshold` value of awscli, 8 MiB.
+///
+/// See the [awscli S3 configuration](https://docs.aws.amazon.com/cli/latest/topic/s3-config.html).
+pub const AWSCLI_DEFAULT_THRESHOLD: NonZeroU64 = NonZeroU64::new(8 << 20).unwrap();
+
+/// The default `multipart_chunksize` value of awscli, 8 MiB.
+///
+/// See the [awscli S3 configuration](https://docs.aws.amazon.com/cli/latest/topic/s3-config.html).
+pub const AWSCLI_DEFAULT_CHUNKSIZE: NonZeroUsize = NonZeroUsize::new(8 << 20).unwrap();
 
 /// A trait that defines the minimum requirements for an underlying MD5 hasher.
 pub trait Md5Hasher: Default {
@@ -25,6 +50,18 @@ pub trait ETagHasher {
     /// Updates the internal state by processing the data.
     fn update(&mut self, data: impl AsRef<[u8]>);
 
+    /// Updates the internal state by processing each chunk of data in order, which is equivalent
+    /// to calling [`ETagHasher::update`] for each chunk.
+    fn update_from_iter<I, B>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        for data in iter {
+            self.update(data);
+        }
+    }
+
     /// Returns the result, consuming the hasher.
     fn finalize(self) -> ETag;
 }
@@ -39,17 +76,81 @@ impl<T: Md5Hasher> ETagHasher for T {
     }
 }
 
+/// A trait for the underlying hash functions of checksums, which generalizes [`Md5Hasher`] to
+/// algorithms with outputs of any length.
+///
+/// Every [`Md5Hasher`] implements this trait with the 16-byte output.
+pub trait ChecksumHasher: Default {
+    /// The length of the output in bytes.
+    const OUTPUT_LEN: usize;
+
+    type Output: AsRef<[u8]>;
+
+    /// Updates the internal state by processing the data.
+    fn update(&mut self, data: impl AsRef<[u8]>);
+
+    /// Returns the result, consuming the hasher.
+    fn finalize(self) -> Self::Output;
+
+    /// Returns the result, reseting the hasher to the initial state.
+    fn finalize_reset(&mut self) -> Self::Output {
+        mem::take(self).finalize()
+    }
+}
+
+impl<T: Md5Hasher> ChecksumHasher for T {
+    const OUTPUT_LEN: usize = 16;
+
+    type Output = T::Output;
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        Md5Hasher::update(self, data)
+    }
+
+    fn finalize(self) -> Self::Output {
+        Md5Hasher::finalize(self)
+    }
+
+    fn finalize_reset(&mut self) -> Self::Output {
+        Md5Hasher::finalize_reset(self)
+    }
+}
+
 /// A hasher state for multipart ETag checksum calculation compatible with [Amazon S3's multipart uploads](https://docs.aws.amazon.com/AmazonS3/latest/userguide/checking-object-integrity.html#large-object-checksums).
-#[derive(Debug)]
+///
+/// This type hashes each chunk and then the concatenation of the chunk digests with the same hash
+/// function `H`, which is the composite checksum construction common to the multipart ETag and
+/// S3's additional checksums. It implements [`ETagHasher`] if `H` is an [`Md5Hasher`], whereas
+/// [`ETagHasherMulti::finalize_composite`] is available for any [`ChecksumHasher`].
+///
+/// This type implements [`Clone`] if the underlying hasher `H` does, allowing a partially fed
+/// hasher state to be forked.
+///
+/// The part count is kept exact however small the chunksize is:
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use s3etag::{ETagHasher as _, ETagHasherMulti};
+/// use std::num::NonZeroUsize;
+///
+/// let mut hasher = ETagHasherMulti::<md5::Md5>::new(NonZeroUsize::new(1).unwrap());
+/// hasher.update(vec![0u8; 123_456]);
+/// assert_eq!(hasher.finalize().part_count(), NonZeroUsize::new(123_456));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
 pub struct ETagHasherMulti<H> {
     chunksize: NonZeroUsize,
     n_chunks: usize,
     hasher_whole: H,
     hasher_chunk: H,
     current_capacity: usize,
+    total_len: u64,
+    #[cfg(feature = "alloc")]
+    part_digests: Option<Vec<u8>>,
 }
 
-impl<H: Md5Hasher> ETagHasherMulti<H> {
+impl<H: ChecksumHasher> ETagHasherMulti<H> {
     /// Creates a new hasher configured for a `multipart_chunksize` value.
     pub fn new(chunksize: NonZeroUsize) -> Self {
         Self {
@@ -58,136 +159,2513 @@ impl<H: Md5Hasher> ETagHasherMulti<H> {
             hasher_whole: Default::default(),
             hasher_chunk: Default::default(),
             current_capacity: chunksize.into(),
+            total_len: 0,
+            #[cfg(feature = "alloc")]
+            part_digests: None,
         }
     }
-}
 
-impl<H: Md5Hasher> ETagHasher for ETagHasherMulti<H> {
-    fn update(&mut self, data: impl AsRef<[u8]>) {
+    /// Creates a new hasher that also records the digest of each chunk, which is retrieved by
+    /// [`ETagHasherMulti::finalize_with_parts`].
+    ///
+    /// The recorded digests take `H::OUTPUT_LEN` bytes of memory per chunk, whereas a hasher
+    /// created by [`ETagHasherMulti::new`] never allocates memory.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn with_part_digests(chunksize: NonZeroUsize) -> Self {
+        Self {
+            part_digests: Some(Vec::new()),
+            ..Self::new(chunksize)
+        }
+    }
+
+    /// Resets the hasher to the initial state, retaining the chunksize configuration and whether
+    /// to record the chunk digests.
+    pub fn reset(&mut self) {
+        #[cfg(feature = "alloc")]
+        let part_digests = self.part_digests.take().map(|mut digests| {
+            digests.clear();
+            digests
+        });
+        *self = Self::new(self.chunksize);
+        #[cfg(feature = "alloc")]
+        {
+            self.part_digests = part_digests;
+        }
+    }
+
+    /// Returns the configured chunksize.
+    pub fn chunksize(&self) -> NonZeroUsize {
+        self.chunksize
+    }
+
+    /// Returns the total number of bytes consumed so far.
+    pub fn bytes_processed(&self) -> u64 {
+        self.total_len
+    }
+
+    /// Returns the number of chunks that have been completely filled and sealed so far, which is
+    /// also the zero-based index of the chunk currently being filled.
+    pub fn current_part_index(&self) -> usize {
+        self.n_chunks
+    }
+
+    /// Updates the internal state by processing the data.
+    ///
+    /// A chunk is sealed as soon as it is filled, so the result is independent of how the data is
+    /// split into calls, including calls that end exactly at or cross chunk boundaries:
+    ///
+    /// ```
+    /// # #[cfg(feature = "md-5")] {
+    /// use s3etag::{ETagHasher as _, ETagHasherMulti};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let data = b"123456789";
+    /// let chunksize = NonZeroUsize::new(4).unwrap();
+    /// let mut expected = ETagHasherMulti::<md5::Md5>::new(chunksize);
+    /// expected.update(data);
+    /// let expected = expected.finalize();
+    ///
+    /// let mut hasher = ETagHasherMulti::<md5::Md5>::new(chunksize);
+    /// hasher.update(&data[..4]); // equal to the remaining capacity
+    /// hasher.update(&data[4..4]);
+    /// hasher.update(&data[4..9]); // larger than the remaining capacity
+    /// assert_eq!(hasher.finalize(), expected);
+    ///
+    /// let mut hasher = ETagHasherMulti::<md5::Md5>::new(chunksize);
+    /// hasher.update(&data[..3]);
+    /// hasher.update(&data[3..4]); // equal to the remaining capacity
+    /// hasher.update(&data[4..9]);
+    /// assert_eq!(hasher.finalize(), expected);
+    /// assert_eq!(expected.part_count(), NonZeroUsize::new(3));
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of chunks overflows `usize`, which is possible only on 16-bit and
+    /// 32-bit targets with an extremely small chunksize, rather than reporting a wrong part count.
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
         let mut buf = data.as_ref();
-        assert!(self.current_capacity > 0);
-        while buf.len() >= self.current_capacity {
-            let used = self.current_capacity;
+        self.total_len += buf.len() as u64;
+        while !buf.is_empty() {
+            let used = buf.len().min(self.current_capacity);
             self.hasher_chunk.update(&buf[..used]);
-            self.n_chunks += 1;
-            self.hasher_whole.update(self.hasher_chunk.finalize_reset());
-            self.current_capacity = self.chunksize.into();
+            self.current_capacity -= used;
+            if self.current_capacity == 0 {
+                self.seal_chunk();
+            }
             buf = &buf[used..];
         }
-        if !buf.is_empty() {
-            self.hasher_chunk.update(buf);
-            self.current_capacity -= buf.len();
+    }
+
+    /// Returns the digest of the concatenated chunk digests and the number of chunks, consuming
+    /// the hasher.
+    ///
+    /// The number of chunks is `None` if this hasher has not consumed any byte, in which case the
+    /// digest is that of the empty input. See [`ETagHasherMulti::finalize`] for the reason.
+    pub fn finalize_composite(mut self) -> (H::Output, Option<NonZeroUsize>) {
+        self.seal_partial_chunk();
+        (
+            self.hasher_whole.finalize(),
+            NonZeroUsize::new(self.n_chunks),
+        )
+    }
+
+    /// Folds the digest of the current chunk into the whole digest and starts the next chunk.
+    fn seal_chunk(&mut self) {
+        self.n_chunks = self.n_chunks.checked_add(1).expect("too many parts");
+        let digest = self.hasher_chunk.finalize_reset();
+        #[cfg(feature = "alloc")]
+        if let Some(digests) = &mut self.part_digests {
+            digests.extend_from_slice(digest.as_ref());
+        }
+        self.hasher_whole.update(digest);
+        self.current_capacity = self.chunksize.into();
+    }
+
+    /// Seals the current chunk if it has consumed any byte.
+    fn seal_partial_chunk(&mut self) {
+        debug_assert!(self.current_capacity <= self.chunksize.into());
+        if self.current_capacity < self.chunksize.into() {
+            self.seal_chunk();
         }
     }
+}
+
+impl<H: Md5Hasher> ETagHasher for ETagHasherMulti<H> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        ETagHasherMulti::update(self, data)
+    }
 
     /// Returns the result, consuming the hasher.
     ///
     /// Note that this method returns a non-multipart ETag if this hasher has not consumed any
     /// byte. This is because awscli does not accept zero multipart_threshold, and thus multipart
     /// uploading is not applicable to an empty file.
-    fn finalize(mut self) -> ETag {
-        assert!(self.current_capacity <= self.chunksize.into());
-        if self.current_capacity < self.chunksize.into() {
-            self.n_chunks += 1;
-            self.hasher_whole.update(self.hasher_chunk.finalize());
-        }
+    fn finalize(self) -> ETag {
+        let (digest, n_chunks) = self.finalize_composite();
         ETag {
-            digest: self.hasher_whole.finalize().into(),
-            n_chunks: self.n_chunks.try_into().ok(),
+            digest: digest.into(),
+            n_chunks,
         }
     }
 }
 
-/// The calculated ETag value type.
+impl<H: Md5Hasher + Clone> ETagHasherMulti<H> {
+    /// Returns the ETag of the data consumed so far as if the hasher were finalized now, leaving
+    /// the hasher intact so that it can continue to consume data.
+    ///
+    /// This method requires `H: Clone` because it finalizes copies of the underlying hasher states,
+    /// and it is therefore more expensive than [`ETagHasherMulti::finalize`].
+    pub fn finalize_peek(&self) -> ETag {
+        ETagHasher::finalize(self.clone())
+    }
+}
+
+impl<H: Md5Hasher> ETagHasherMulti<H> {
+    /// Returns the ETag and the MD5 digest of each part, consuming the hasher.
+    ///
+    /// The part digests are recorded only by a hasher created by
+    /// [`ETagHasherMulti::with_part_digests`]; the returned list is empty otherwise. It is also
+    /// empty if this hasher has not consumed any byte, in which case the ETag is single-part.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "md-5")] {
+    /// use s3etag::{ETagHasher as _, ETagHasherMulti};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let mut hasher = ETagHasherMulti::<md5::Md5>::with_part_digests(NonZeroUsize::new(4).unwrap());
+    /// hasher.update(b"123456789");
+    /// let (etag, parts) = hasher.finalize_with_parts();
+    /// assert_eq!(etag.to_string(), "393e928fcf5925fcbd3a06aaf20b2d38-3");
+    /// assert_eq!(parts.len(), 3);
+    /// assert_eq!(parts[2], s3etag::etag_single::<md5::Md5>(b"9").digest());
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn finalize_with_parts(mut self) -> (ETag, Vec<[u8; 16]>) {
+        self.seal_partial_chunk();
+        let parts = self
+            .part_digests
+            .take()
+            .unwrap_or_default()
+            .chunks_exact(16)
+            .map(|digest| digest.try_into().unwrap())
+            .collect();
+        (ETagHasher::finalize(self), parts)
+    }
+
+    /// Creates a new hasher for data of `len` bytes, selecting the mode immediately from `len` and
+    /// a `multipart_threshold` value.
+    ///
+    /// Unlike [`S3Etagger::new`], the returned hasher does not buffer any data. This is equivalent
+    /// to [`S3Etagger::for_known_size`] except that the selected mode is exposed to the caller.
+    /// The resulting ETag is not meaningful if the length of the data fed to the hasher differs
+    /// from `len`.
+    pub fn new_for_size(
+        len: u64,
+        threshold: NonZeroU64,
+        chunksize: NonZeroUsize,
+    ) -> SizedHasher<H> {
+        if len < threshold.get() {
+            SizedHasher::Single(Default::default())
+        } else {
+            SizedHasher::Multi(Self::new(chunksize))
+        }
+    }
+}
+
+/// A hasher state whose mode has been determined from the known length of the data, returned by
+/// [`ETagHasherMulti::new_for_size`].
+#[derive(Clone, Debug)]
+pub enum SizedHasher<H> {
+    /// The single-part mode, which computes the plain MD5 digest.
+    Single(H),
+    /// The multipart mode.
+    Multi(ETagHasherMulti<H>),
+}
+
+impl<H> SizedHasher<H> {
+    /// Returns the chunksize in the multipart mode, or `None` in the single-part mode.
+    pub fn chunksize(&self) -> Option<NonZeroUsize> {
+        match self {
+            Self::Single(_) => None,
+            Self::Multi(hasher) => Some(hasher.chunksize),
+        }
+    }
+}
+
+impl<H: Md5Hasher> ETagHasher for SizedHasher<H> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            Self::Single(hasher) => Md5Hasher::update(hasher, data),
+            Self::Multi(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> ETag {
+        match self {
+            Self::Single(hasher) => ETagHasher::finalize(hasher),
+            Self::Multi(hasher) => ETagHasher::finalize(hasher),
+        }
+    }
+}
+
+/// A hasher state that selects between single-part and multipart ETag calculation based on a
+/// `multipart_threshold` value, as awscli does.
+///
+/// awscli uploads a file as a single part if the file size is less than `multipart_threshold` and
+/// as multiple parts otherwise.
+///
+/// Thus, data of exactly `threshold` bytes results in a multipart ETag of one part, whereas empty
+/// data always results in the plain MD5 digest of the empty input without a part count:
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use s3etag::{ETagHasher as _, S3Etagger};
+/// use std::num::{NonZeroU64, NonZeroUsize};
+///
+/// let threshold = NonZeroU64::new(9).unwrap();
+/// let chunksize = NonZeroUsize::new(8 << 20).unwrap();
+///
+/// let mut hasher = S3Etagger::<md5::Md5>::for_known_size(9, threshold, chunksize);
+/// hasher.update(b"123456789");
+/// assert_eq!(hasher.finalize().to_string(), "5927c5d64d94a5786f90003aa26d0159-1");
+///
+/// let mut hasher = S3Etagger::<md5::Md5>::for_known_size(8, threshold, chunksize);
+/// hasher.update(b"12345678");
+/// assert!(!hasher.finalize().is_multipart());
+///
+/// # #[cfg(feature = "alloc")] {
+/// let hasher = S3Etagger::<md5::Md5>::new(NonZeroU64::MIN, chunksize);
+/// assert_eq!(hasher.finalize().to_string(), "d41d8cd98f00b204e9800998ecf8427e");
+/// # }
+/// # }
+/// ```
 #[derive(Debug)]
-pub struct ETag {
-    digest: [u8; 16],
-    n_chunks: Option<NonZeroUsize>,
+pub struct S3Etagger<H> {
+    state: EtaggerState<H>,
+    threshold: Option<NonZeroU64>,
 }
 
-impl fmt::Display for ETag {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use fmt::Write as _;
-        let mut buf = ArrayString::<64>::new();
-        for e in self.digest {
-            write!(buf, "{:02x}", e)?;
+#[derive(Debug)]
+enum EtaggerState<H> {
+    Single(H),
+    Multi(ETagHasherMulti<H>),
+    #[cfg(feature = "alloc")]
+    Adaptive(AdaptiveETagHasher<H>),
+}
+
+impl<H: Md5Hasher> S3Etagger<H> {
+    /// Creates a new hasher for a stream of unknown length configured for `multipart_threshold`
+    /// and `multipart_chunksize` values.
+    ///
+    /// Since the mode cannot be determined until the stream length reaches the threshold, this
+    /// hasher buffers up to `threshold` bytes in memory before it switches to the multipart mode,
+    /// just as [`AdaptiveETagHasher`] does. Use [`S3Etagger::for_known_size`] instead if the total
+    /// length is known in advance.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn new(threshold: NonZeroU64, chunksize: NonZeroUsize) -> Self {
+        Self {
+            state: EtaggerState::Adaptive(AdaptiveETagHasher::new(threshold, chunksize)),
+            threshold: Some(threshold),
         }
-        if let Some(n) = self.n_chunks {
-            write!(buf, "-{}", n)?;
+    }
+
+    /// Creates a new hasher whose mode is determined immediately from the total length of the data
+    /// to be processed.
+    ///
+    /// The resulting ETag is not meaningful if the length of the data fed to the hasher differs
+    /// from `len`.
+    pub fn for_known_size(len: u64, threshold: NonZeroU64, chunksize: NonZeroUsize) -> Self {
+        let hasher = if len < threshold.get() {
+            Self::single()
+        } else {
+            Self::multipart(chunksize)
+        };
+        Self {
+            threshold: Some(threshold),
+            ..hasher
+        }
+    }
+
+    /// Creates a new hasher that always computes the single-part ETag, i.e., the plain MD5 digest,
+    /// regardless of the data length.
+    pub fn single() -> Self {
+        Self {
+            state: EtaggerState::Single(Default::default()),
+            threshold: None,
+        }
+    }
+
+    /// Creates a new hasher that always computes the multipart ETag of `chunksize`-byte parts
+    /// regardless of the data length, as [`ETagHasherMulti`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "md-5")] {
+    /// use s3etag::{ETagHasher as _, S3Etagger};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let mut hasher = S3Etagger::<md5::Md5>::multipart(NonZeroUsize::new(8 << 20).unwrap());
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "5927c5d64d94a5786f90003aa26d0159-1");
+    /// # }
+    /// ```
+    pub fn multipart(chunksize: NonZeroUsize) -> Self {
+        Self {
+            state: EtaggerState::Multi(ETagHasherMulti::new(chunksize)),
+            threshold: None,
+        }
+    }
+
+    /// Returns the threshold that the hasher was created with, or `None` if the mode was given by
+    /// [`S3Etagger::single`] or [`S3Etagger::multipart`].
+    pub fn threshold(&self) -> Option<NonZeroU64> {
+        self.threshold
+    }
+
+    /// Returns the chunksize used in the multipart mode, or `None` if the mode is single-part.
+    ///
+    /// A hasher for a stream of unknown length returns the chunksize to be used if the stream
+    /// reaches the threshold.
+    pub fn chunksize(&self) -> Option<NonZeroUsize> {
+        match &self.state {
+            EtaggerState::Single(_) => None,
+            EtaggerState::Multi(hasher) => Some(hasher.chunksize),
+            #[cfg(feature = "alloc")]
+            EtaggerState::Adaptive(hasher) => Some(hasher.chunksize()),
         }
-        fmt::Display::fmt(buf.as_str(), f)
     }
 }
 
-impl From<[u8; 16]> for ETag {
-    fn from(digest: [u8; 16]) -> Self {
+impl<H: Md5Hasher> ETagHasher for S3Etagger<H> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        match &mut self.state {
+            EtaggerState::Single(hasher) => Md5Hasher::update(hasher, data),
+            EtaggerState::Multi(hasher) => hasher.update(data),
+            #[cfg(feature = "alloc")]
+            EtaggerState::Adaptive(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> ETag {
+        match self.state {
+            EtaggerState::Single(hasher) => ETagHasher::finalize(hasher),
+            EtaggerState::Multi(hasher) => hasher.finalize(),
+            #[cfg(feature = "alloc")]
+            EtaggerState::Adaptive(hasher) => hasher.finalize(),
+        }
+    }
+}
+
+/// A builder that holds the multipart parameters and the underlying MD5 implementation `H` and
+/// creates [`S3Etagger`] instances configured with them.
+///
+/// The defaults are those of awscli: [`AWSCLI_DEFAULT_THRESHOLD`] and
+/// [`AWSCLI_DEFAULT_CHUNKSIZE`], without the chunksize adjustment.
+///
+/// The hasher is returned as the concrete [`S3Etagger`] type rather than a boxed trait object
+/// because [`ETagHasher`] takes generic arguments and is not dyn-compatible.
+#[derive(Clone, Debug)]
+pub struct ETagConfig<H> {
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+    auto_chunksize: bool,
+    _hasher: PhantomData<fn() -> H>,
+}
+
+impl<H: Md5Hasher> ETagConfig<H> {
+    /// Creates a new builder with the default parameters.
+    pub fn new() -> Self {
         Self {
-            digest,
-            n_chunks: None,
+            threshold: AWSCLI_DEFAULT_THRESHOLD,
+            chunksize: AWSCLI_DEFAULT_CHUNKSIZE,
+            auto_chunksize: false,
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Sets the `multipart_threshold` value.
+    pub fn threshold(mut self, threshold: NonZeroU64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the `multipart_chunksize` value.
+    pub fn chunksize(mut self, chunksize: NonZeroUsize) -> Self {
+        self.chunksize = chunksize;
+        self
+    }
+
+    /// Enables or disables the adjustment of the chunksize by [`adjust_chunksize`].
+    pub fn auto_chunksize(mut self, auto_chunksize: bool) -> Self {
+        self.auto_chunksize = auto_chunksize;
+        self
+    }
+
+    /// Replaces the underlying MD5 implementation, keeping the other parameters.
+    pub fn algorithm<T: Md5Hasher>(self) -> ETagConfig<T> {
+        ETagConfig {
+            threshold: self.threshold,
+            chunksize: self.chunksize,
+            auto_chunksize: self.auto_chunksize,
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Returns the chunksize used for data of `len` bytes, or of unknown length if `None`.
+    pub fn chunksize_for(&self, len: Option<u64>) -> NonZeroUsize {
+        if self.auto_chunksize {
+            adjust_chunksize(self.chunksize, len)
+        } else {
+            self.chunksize
         }
     }
+
+    /// Creates a new hasher for data of unknown length.
+    ///
+    /// See [`S3Etagger::new`] for the memory consumption of this hasher.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn build_hasher(&self) -> S3Etagger<H> {
+        S3Etagger::new(self.threshold, self.chunksize_for(None))
+    }
+
+    /// Creates a new hasher for data of `len` bytes.
+    pub fn build_hasher_for_size(&self, len: u64) -> S3Etagger<H> {
+        S3Etagger::for_known_size(len, self.threshold, self.chunksize_for(Some(len)))
+    }
 }
 
-#[cfg(feature = "md-5")]
-#[cfg_attr(docsrs, doc(cfg(feature = "md-5")))]
-impl Md5Hasher for md5::Md5 {
-    type Output = md5::digest::Output<Self>;
+impl<H: Md5Hasher> Default for ETagConfig<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    fn update(&mut self, data: impl AsRef<[u8]>) {
-        md5::Digest::update(self, data)
+/// A hasher state that computes the correct ETag for a stream of unknown length by determining
+/// the mode from a `multipart_threshold` value at the end of the stream.
+///
+/// This hasher buffers incoming data up to `threshold` bytes. If the stream ends before reaching
+/// the threshold, it finalizes the buffered data as a single-part ETag. Otherwise, it replays the
+/// buffered data to an [`ETagHasherMulti`] and continues in the multipart mode, so the memory
+/// consumption is bounded by `threshold`. A stream of exactly `threshold` bytes results in a
+/// multipart ETag, as awscli does.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Debug)]
+pub struct AdaptiveETagHasher<H> {
+    threshold: NonZeroU64,
+    state: AdaptiveState<H>,
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+enum AdaptiveState<H> {
+    Buffering {
+        buffer: Vec<u8>,
+        chunksize: NonZeroUsize,
+    },
+    Multi(ETagHasherMulti<H>),
+}
+
+#[cfg(feature = "alloc")]
+impl<H: Md5Hasher> AdaptiveETagHasher<H> {
+    /// Creates a new hasher configured for `multipart_threshold` and `multipart_chunksize` values.
+    pub fn new(threshold: NonZeroU64, chunksize: NonZeroUsize) -> Self {
+        Self {
+            threshold,
+            state: AdaptiveState::Buffering {
+                buffer: Vec::new(),
+                chunksize,
+            },
+        }
     }
 
-    fn finalize(self) -> Self::Output {
-        md5::Digest::finalize(self)
+    /// Returns the configured threshold.
+    pub fn threshold(&self) -> NonZeroU64 {
+        self.threshold
     }
 
-    fn finalize_reset(&mut self) -> Self::Output {
-        md5::Digest::finalize_reset(self)
+    /// Returns the configured chunksize.
+    pub fn chunksize(&self) -> NonZeroUsize {
+        match &self.state {
+            AdaptiveState::Buffering { chunksize, .. } => *chunksize,
+            AdaptiveState::Multi(hasher) => hasher.chunksize,
+        }
     }
 }
 
-#[cfg(feature = "openssl")]
-pub use openssl_bindings::OpensslMd5;
+#[cfg(feature = "alloc")]
+impl<H: Md5Hasher> ETagHasher for AdaptiveETagHasher<H> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        match &mut self.state {
+            AdaptiveState::Multi(hasher) => hasher.update(data),
+            AdaptiveState::Buffering { buffer, chunksize } => {
+                if ((buffer.len() + data.len()) as u64) < self.threshold.get() {
+                    buffer.extend_from_slice(data);
+                } else {
+                    let mut hasher = ETagHasherMulti::new(*chunksize);
+                    hasher.update(mem::take(buffer));
+                    hasher.update(data);
+                    self.state = AdaptiveState::Multi(hasher);
+                }
+            }
+        }
+    }
 
-#[cfg(feature = "openssl")]
-#[cfg_attr(docsrs, doc(cfg(feature = "openssl")))]
-mod openssl_bindings {
-    use openssl::{md::Md, md_ctx::MdCtx};
+    fn finalize(self) -> ETag {
+        match self.state {
+            AdaptiveState::Multi(hasher) => hasher.finalize(),
+            AdaptiveState::Buffering { buffer, .. } => {
+                let mut hasher = H::default();
+                Md5Hasher::update(&mut hasher, buffer);
+                ETagHasher::finalize(hasher)
+            }
+        }
+    }
+}
 
-    use super::Md5Hasher;
+/// A hasher state for S3's additional checksums, such as `x-amz-checksum-sha256`, based on an
+/// arbitrary [`ChecksumHasher`].
+///
+/// A full-object hasher computes the plain digest of the whole data, which S3 reports for an
+/// object uploaded in a single part, whereas a composite hasher computes the digest of the
+/// concatenated part digests in the same way as the multipart ETag. This type also provides the
+/// streaming mode that selects between the two by a `multipart_threshold` value, as
+/// [`S3Etagger::new`] does.
+#[derive(Clone, Debug)]
+pub struct CompositeHasher<H> {
+    state: CompositeState<H>,
+}
 
-    /// A wrapper for OpenSSL's `EVP_MD_CTX` object to implement [`Md5Hasher`].
-    ///
-    /// Note that implemented trait methods of this type may panic if the underlying OpenSSL
-    /// functions unexpectedly return an error.
-    pub struct OpensslMd5(MdCtx);
+#[derive(Clone, Debug)]
+enum CompositeState<H> {
+    FullObject(H),
+    Composite(ETagHasherMulti<H>),
+    #[cfg(feature = "alloc")]
+    Buffering {
+        buffer: Vec<u8>,
+        threshold: NonZeroU64,
+        chunksize: NonZeroUsize,
+    },
+}
 
-    impl Default for OpensslMd5 {
-        fn default() -> Self {
-            let mut ctx = MdCtx::new().expect("openssl error");
-            ctx.digest_init(Md::md5()).expect("openssl error");
-            Self(ctx)
+impl<H: ChecksumHasher> CompositeHasher<H> {
+    /// Creates a new hasher for a stream of unknown length configured for `multipart_threshold`
+    /// and `multipart_chunksize` values, buffering up to `threshold` bytes in memory.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn new(threshold: NonZeroU64, chunksize: NonZeroUsize) -> Self {
+        Self {
+            state: CompositeState::Buffering {
+                buffer: Vec::new(),
+                threshold,
+                chunksize,
+            },
         }
     }
 
-    impl Md5Hasher for OpensslMd5 {
-        type Output = [u8; 16];
+    /// Creates a new hasher for the checksum of an object uploaded in a single part.
+    pub fn full_object() -> Self {
+        Self {
+            state: CompositeState::FullObject(Default::default()),
+        }
+    }
 
-        fn update(&mut self, data: impl AsRef<[u8]>) {
-            self.0.digest_update(data.as_ref()).expect("openssl error");
+    /// Creates a new hasher for the composite checksum of a multipart upload configured for a
+    /// `multipart_chunksize` value.
+    pub fn composite(chunksize: NonZeroUsize) -> Self {
+        Self {
+            state: CompositeState::Composite(ETagHasherMulti::new(chunksize)),
         }
+    }
 
-        fn finalize(mut self) -> Self::Output {
-            let mut buffer = [0; 16];
-            self.0.digest_final(&mut buffer).expect("openssl error");
-            buffer
+    /// Creates a new hasher whose mode is determined immediately from the total length of the data
+    /// to be processed, as [`S3Etagger::for_known_size`] does.
+    pub fn for_known_size(len: u64, threshold: NonZeroU64, chunksize: NonZeroUsize) -> Self {
+        if len < threshold.get() {
+            Self::full_object()
+        } else {
+            Self::composite(chunksize)
         }
+    }
 
-        fn finalize_reset(&mut self) -> Self::Output {
-            let mut buffer = [0; 16];
-            self.0.digest_final(&mut buffer).expect("openssl error");
-            self.0.digest_init(Md::md5()).expect("openssl error");
-            buffer
+    /// Updates the internal state by processing the data.
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        match &mut self.state {
+            CompositeState::FullObject(hasher) => ChecksumHasher::update(hasher, data),
+            CompositeState::Composite(hasher) => hasher.update(data),
+            #[cfg(feature = "alloc")]
+            CompositeState::Buffering {
+                buffer,
+                threshold,
+                chunksize,
+            } => {
+                if ((buffer.len() + data.len()) as u64) < threshold.get() {
+                    buffer.extend_from_slice(data);
+                } else {
+                    let mut hasher = ETagHasherMulti::new(*chunksize);
+                    hasher.update(mem::take(buffer));
+                    hasher.update(data);
+                    self.state = CompositeState::Composite(hasher);
+                }
+            }
+        }
+    }
+
+    /// Returns the result, consuming the hasher.
+    ///
+    /// As with the multipart ETag, a composite hasher that has not consumed any byte returns a
+    /// full-object checksum.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the output of `H` is longer than [`Checksum::MAX_LEN`].
+    pub fn finalize(self) -> Checksum {
+        let (digest, n_chunks) = match self.state {
+            CompositeState::FullObject(hasher) => (ChecksumHasher::finalize(hasher), None),
+            CompositeState::Composite(hasher) => hasher.finalize_composite(),
+            #[cfg(feature = "alloc")]
+            CompositeState::Buffering { buffer, .. } => {
+                let mut hasher = H::default();
+                ChecksumHasher::update(&mut hasher, buffer);
+                (ChecksumHasher::finalize(hasher), None)
+            }
+        };
+        Checksum {
+            digest: digest.as_ref().try_into().expect("too long checksum"),
+            n_chunks,
+        }
+    }
+}
+
+/// A hasher state for S3's full-object checksums, such as `x-amz-checksum-crc64nvme`, based on
+/// an arbitrary [`ChecksumHasher`].
+///
+/// Unlike [`CompositeHasher`], this hasher digests the whole byte stream regardless of part
+/// boundaries, and thus the resulting [`Checksum`] never has the `-N` suffix even if the object is
+/// uploaded in multiple parts.
+#[derive(Clone, Debug, Default)]
+pub struct FullObjectHasher<H>(H);
+
+impl<H: ChecksumHasher> FullObjectHasher<H> {
+    /// Creates a new hasher.
+    pub fn new() -> Self {
+        Self(Default::default())
+    }
+
+    /// Updates the internal state by processing the data.
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        ChecksumHasher::update(&mut self.0, data)
+    }
+
+    /// Returns the result, consuming the hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the output of `H` is longer than [`Checksum::MAX_LEN`].
+    pub fn finalize(self) -> Checksum {
+        Checksum {
+            digest: ChecksumHasher::finalize(self.0)
+                .as_ref()
+                .try_into()
+                .expect("too long checksum"),
+            n_chunks: None,
+        }
+    }
+}
+
+/// Returns the chunksize that awscli actually uses to upload an object of `size` bytes when
+/// `multipart_chunksize` is configured to `chunksize`.
+///
+/// Like `s3transfer`'s `ChunksizeAdjuster`, this function doubles the chunksize until the number
+/// of parts does not exceed [`MAX_PART_COUNT`] and then clamps the result to the range from 5 MiB
+/// to 5 GiB, the part size limits of S3. Only the clamping applies if `size` is unknown as in the
+/// case of streaming uploads.
+pub fn adjust_chunksize(chunksize: NonZeroUsize, size: Option<u64>) -> NonZeroUsize {
+    const MIN_SIZE: u64 = 5 << 20;
+    const MAX_SIZE: u64 = 5 << 30;
+    let mut chunksize = chunksize.get() as u64;
+    if let Some(size) = size {
+        while size.div_ceil(chunksize) > MAX_PART_COUNT as u64 {
+            chunksize *= 2;
+        }
+    }
+    let chunksize = chunksize.clamp(MIN_SIZE, MAX_SIZE);
+    // `MAX_SIZE` may exceed `usize::MAX` on 32-bit targets.
+    NonZeroUsize::new(chunksize.try_into().unwrap_or(usize::MAX)).unwrap()
+}
+
+/// Returns candidate `multipart_chunksize` values that split an object of `size` bytes into exactly
+/// `part_count` parts, which is useful to find out the chunksize used to upload an object from its
+/// multipart ETag.
+///
+/// This function returns every chunksize that yields `part_count` parts if hashing the object once
+/// for each of them amounts to at most 1 GiB in total, which is typically the case for small
+/// objects. Otherwise, the candidates examined are the powers of two from 1 MiB to 4 GiB, which
+/// cover the awscli default of 8 MiB as well as its automatic adjustments for large files, and the
+/// smallest chunksize that yields `part_count` parts, both as-is and rounded up to a multiple of 1
+/// MiB. This function returns those satisfying the part count in ascending order.
+///
+/// # Examples
+///
+/// ```
+/// use std::num::NonZeroUsize;
+///
+/// let candidates = s3etag::chunksize_candidates(9, NonZeroUsize::new(3).unwrap());
+/// assert_eq!(candidates, [3, 4].map(|c| NonZeroUsize::new(c).unwrap()));
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn chunksize_candidates(size: u64, part_count: NonZeroUsize) -> Vec<NonZeroUsize> {
+    const MIB: u64 = 1 << 20;
+    const EXHAUSTIVE_LIMIT: u64 = 1 << 30;
+    let n = part_count.get() as u64;
+    let smallest = size.div_ceil(n);
+    // A chunksize `c` yields `n` parts if `(n - 1) * c < size <= n * c`. Any chunksize of at least
+    // `size` yields the same single part, so only the smallest one is needed for `n == 1`.
+    if n > 1 && size > 0 {
+        let largest = (size - 1) / (n - 1);
+        let count = (largest + 1).saturating_sub(smallest);
+        if count.saturating_mul(size) <= EXHAUSTIVE_LIMIT {
+            return (smallest..=largest)
+                .filter_map(|c| usize::try_from(c).ok().and_then(NonZeroUsize::new))
+                .collect();
+        }
+    }
+    let mut candidates = (0..=12)
+        .map(|i| MIB << i)
+        .chain([smallest, smallest.div_ceil(MIB) * MIB])
+        .filter(|&c| c > 0 && size.div_ceil(c) == part_count.get() as u64)
+        .filter_map(|c| usize::try_from(c).ok().and_then(NonZeroUsize::new))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Returns the number of parts that the ETag of an object of `size` bytes indicates when uploaded
+/// with `multipart_threshold` and `multipart_chunksize` values, without reading any data.
+///
+/// This function returns `None` for a single-part upload and saturates at `usize::MAX` on targets
+/// where the count does not fit. As with [`chunk_plan`], the chunksize is used as-is.
+pub fn expected_part_count(
+    size: u64,
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+) -> Option<NonZeroUsize> {
+    if size < threshold.get() {
+        return None;
+    }
+    let n = size.div_ceil(chunksize.get() as u64);
+    NonZeroUsize::new(n.try_into().unwrap_or(usize::MAX))
+}
+
+/// Returns the layout of the parts into which awscli splits an object of `size` bytes when
+/// configured with `multipart_threshold` and `multipart_chunksize` values, without reading any
+/// data.
+///
+/// The chunksize is used as-is; apply [`adjust_chunksize`] beforehand to reproduce the automatic
+/// adjustment by awscli.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn chunk_plan(size: u64, threshold: NonZeroU64, chunksize: NonZeroUsize) -> ChunkPlan {
+    if size < threshold.get() {
+        return ChunkPlan {
+            parts: Vec::from([(0, size)]),
+            multipart: false,
+        };
+    }
+    let parts = (0..size)
+        .step_by(chunksize.get())
+        .map(|offset| (offset, (size - offset).min(chunksize.get() as u64)))
+        .collect();
+    ChunkPlan {
+        parts,
+        multipart: true,
+    }
+}
+
+/// The layout of the parts of an object returned by [`chunk_plan`].
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkPlan {
+    /// The offset and length of each part in bytes. A single-part upload consists of one part that
+    /// covers the whole object.
+    pub parts: Vec<(u64, u64)>,
+    /// Whether the object is uploaded in the multipart mode.
+    pub multipart: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl ChunkPlan {
+    /// Returns the number of parts that the resulting ETag indicates, or `None` for a single-part
+    /// upload, just as [`ETag::part_count`] does.
+    pub fn part_count(&self) -> Option<NonZeroUsize> {
+        if self.multipart {
+            NonZeroUsize::new(self.parts.len())
+        } else {
+            None
+        }
+    }
+}
+
+/// Computes the ETag of in-memory data, selecting the mode from the data length and a
+/// `multipart_threshold` value as [`S3Etagger`] does.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use std::num::{NonZeroU64, NonZeroUsize};
+///
+/// let chunksize = NonZeroUsize::new(4).unwrap();
+///
+/// // Data shorter than the threshold results in a single-part ETag.
+/// let threshold = NonZeroU64::new(10).unwrap();
+/// let etag = s3etag::etag::<md5::Md5>(b"123456789", threshold, chunksize);
+/// assert_eq!(etag.to_string(), "25f9e794323b453885f5181f1b624d0b");
+///
+/// // Data of exactly the threshold length is split into parts.
+/// let threshold = NonZeroU64::new(9).unwrap();
+/// let etag = s3etag::etag::<md5::Md5>(b"123456789", threshold, chunksize);
+/// assert_eq!(etag.to_string(), "393e928fcf5925fcbd3a06aaf20b2d38-3");
+/// # }
+/// ```
+pub fn etag<H: Md5Hasher>(data: &[u8], threshold: NonZeroU64, chunksize: NonZeroUsize) -> ETag {
+    let mut hasher = S3Etagger::<H>::for_known_size(data.len() as u64, threshold, chunksize);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Computes the single-part ETag of in-memory data, which is the plain MD5 digest.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// let etag = s3etag::etag_single::<md5::Md5>(b"");
+/// assert_eq!(etag.to_string(), "d41d8cd98f00b204e9800998ecf8427e");
+/// # }
+/// ```
+pub fn etag_single<H: Md5Hasher>(data: &[u8]) -> ETag {
+    let mut hasher = H::default();
+    Md5Hasher::update(&mut hasher, data);
+    ETagHasher::finalize(hasher)
+}
+
+/// Computes the multipart ETag of in-memory data split into `chunksize`-byte parts.
+///
+/// As with [`ETagHasherMulti`], empty data results in the single-part ETag.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use std::num::NonZeroUsize;
+///
+/// let chunksize = NonZeroUsize::new(8 << 20).unwrap();
+///
+/// let etag = s3etag::etag_multipart::<md5::Md5>(b"123456789", chunksize);
+/// assert_eq!(etag.to_string(), "5927c5d64d94a5786f90003aa26d0159-1");
+///
+/// let etag = s3etag::etag_multipart::<md5::Md5>(b"", chunksize);
+/// assert_eq!(etag.to_string(), "d41d8cd98f00b204e9800998ecf8427e");
+/// # }
+/// ```
+pub fn etag_multipart<H: Md5Hasher>(data: &[u8], chunksize: NonZeroUsize) -> ETag {
+    let mut hasher = ETagHasherMulti::<H>::new(chunksize);
+    hasher.update(data);
+    ETagHasher::finalize(hasher)
+}
+
+/// Combines the ETags of a set of files into a single value in a custom scheme, which is not
+/// related to any checksum of S3.
+///
+/// Each ETag is encoded as a line of the 32 lowercase hexadecimal digits of the digest, followed,
+/// for a multipart ETag, by `-` and the part count in decimal without leading zeros, and
+/// terminated by a newline (`\n`). The lines are sorted in the byte order and concatenated, and
+/// the result is the single-part ETag, i.e., the MD5 digest, of the concatenation. The value is
+/// thus independent of the order of `etags` and equals the output of `sort | md5sum` applied to the
+/// list of ETags in the C locale.
+///
+/// This byte layout is a stable contract, which is defined independently of the [`fmt::Display`]
+/// implementation of [`ETag`] so that combined values remain comparable across versions.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use s3etag::{combine_etags, etag_single};
+///
+/// let a = etag_single::<md5::Md5>(b"");
+/// let b = etag_single::<md5::Md5>(b"123456789");
+/// let combined = combine_etags::<md5::Md5>([a, b]);
+/// assert_eq!(combined, combine_etags::<md5::Md5>([b, a]));
+/// assert_eq!(combined.to_string(), "9e617cf877883048f89f12d1182de19d");
+///
+/// // The digest of "393e928fcf5925fcbd3a06aaf20b2d38-3\n".
+/// let c = "393e928fcf5925fcbd3a06aaf20b2d38-3".parse().unwrap();
+/// let combined = combine_etags::<md5::Md5>([c]);
+/// assert_eq!(combined.to_string(), "5ca8d9966e3cca75c7c496e1ec63187c");
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn combine_etags<H: Md5Hasher>(etags: impl IntoIterator<Item = ETag>) -> ETag {
+    use fmt::Write as _;
+    let mut lines = etags
+        .into_iter()
+        .map(|etag| {
+            let mut line = ArrayString::<64>::new();
+            for byte in etag.digest {
+                write!(line, "{:02x}", byte).unwrap();
+            }
+            if let Some(n_chunks) = etag.n_chunks {
+                write!(line, "-{}", n_chunks).unwrap();
+            }
+            line.push('\n');
+            line
+        })
+        .collect::<Vec<_>>();
+    lines.sort();
+
+    let mut hasher = H::default();
+    for line in &lines {
+        Md5Hasher::update(&mut hasher, line.as_bytes());
+    }
+    ETagHasher::finalize(hasher)
+}
+
+/// Computes the ETag of fully materialized data, hashing the chunks in parallel using the
+/// [`rayon`] global thread pool.
+///
+/// This function yields the same result as [`S3Etagger`] fed with `data` but is applicable only to
+/// data that fits in memory or is memory-mapped. The chunk digests are combined in the original
+/// order regardless of the order in which the chunks are hashed.
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn etag_parallel<H: Md5Hasher>(
+    data: &[u8],
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+) -> ETag {
+    use rayon::prelude::*;
+
+    if (data.len() as u64) < threshold.get() {
+        let mut hasher = H::default();
+        Md5Hasher::update(&mut hasher, data);
+        return ETagHasher::finalize(hasher);
+    }
+
+    let digests: Vec<[u8; 16]> = data
+        .par_chunks(chunksize.get())
+        .map(|chunk| {
+            let mut hasher = H::default();
+            Md5Hasher::update(&mut hasher, chunk);
+            Md5Hasher::finalize(hasher).into()
+        })
+        .collect();
+    ETag::from_part_digests::<H>(&digests)
+}
+
+/// Computes the ETag of the entire content read from a [`tokio::io::AsyncRead`] stream.
+///
+/// Since the stream length is unknown in advance, this function uses the streaming mode of
+/// [`S3Etagger`] and thus buffers up to `threshold` bytes in memory. The MD5 computation is
+/// performed on the calling task, but it is bounded by the 64 KiB read buffer per read, so
+/// offloading to `spawn_blocking` is usually unnecessary unless the reader yields data faster than
+/// MD5 can process and starves other tasks.
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub async fn etag_from_async_read<H, R>(
+    mut reader: R,
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+) -> io::Result<ETag>
+where
+    H: Md5Hasher,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt as _;
+
+    let mut hasher = S3Etagger::<H>::new(threshold, chunksize);
+    let mut buffer = vec![0u8; 64 * 1024].into_boxed_slice();
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => break Ok(hasher.finalize()),
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => break Err(e),
+        }
+    }
+}
+
+/// Computes the plain MD5 digest of the `len` bytes at `offset` in a seekable reader, which equals
+/// the digest of the part at the same position of a multipart upload.
+///
+/// The result is returned as a single-part ETag. This function returns an error of the
+/// [`io::ErrorKind::UnexpectedEof`] kind if the reader ends before the end of the range.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn etag_of_range<H: Md5Hasher>(
+    mut reader: impl io::Read + io::Seek,
+    offset: u64,
+    len: u64,
+) -> io::Result<ETag> {
+    reader.seek(io::SeekFrom::Start(offset))?;
+    let mut writer = WriteHasher::new(H::default());
+    if io::copy(&mut reader.take(len), &mut writer)? < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(writer.finalize())
+}
+
+/// Computes the ETag of a file from the current position to the end, selecting the mode from the
+/// file size as [`S3Etagger::for_known_size`] does and reading through `buffer`.
+///
+/// The file size is the logical length reported by the metadata, so the file should be at its
+/// start. Since the ETag is meaningless if the file grows or shrinks while being hashed, this
+/// function returns an error of the [`io::ErrorKind::InvalidData`] kind if the number of bytes
+/// read differs from the size. The file is read sequentially; advising the kernel of the access
+/// pattern, e.g., by `posix_fadvise(2)`, is left to the caller.
+///
+/// # Panics
+///
+/// Panics if `buffer` is empty.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "md-5")] {
+/// use std::fs;
+///
+/// let mut file = fs::File::open("file.bin")?;
+/// let (threshold, chunksize) = (s3etag::AWSCLI_DEFAULT_THRESHOLD, s3etag::AWSCLI_DEFAULT_CHUNKSIZE);
+/// let mut buffer = vec![0u8; 64 * 1024];
+/// let etag = s3etag::etag_from_file::<md5::Md5>(&mut file, threshold, chunksize, &mut buffer)?;
+/// println!("{}", etag);
+/// # }
+/// # Ok::<(), s3etag::ETagError>(())
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn etag_from_file<H: Md5Hasher>(
+    file: &mut std::fs::File,
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+    buffer: &mut [u8],
+) -> Result<ETag, ETagError> {
+    let size = file.metadata()?.len();
+    let (etag, len) = etag_from_read::<H>(file, size, threshold, chunksize, buffer)?;
+    if len != size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file size changed while being hashed",
+        )
+        .into());
+    }
+    Ok(etag)
+}
+
+/// Computes the ETag of the entire content of a reader whose length is known to be `size` bytes,
+/// selecting the mode from `size` as [`S3Etagger::for_known_size`] does and reading through
+/// `buffer`.
+///
+/// This function returns the ETag with the number of bytes actually read, which the caller should
+/// compare with `size` because the ETag is meaningless if they differ. [`etag_from_file`] is built
+/// on this function, which in turn accepts a reader wrapped in adapters, e.g., to show progress.
+///
+/// # Panics
+///
+/// Panics if `buffer` is empty.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn etag_from_read<H: Md5Hasher>(
+    mut reader: impl io::Read,
+    size: u64,
+    threshold: NonZeroU64,
+    chunksize: NonZeroUsize,
+    buffer: &mut [u8],
+) -> Result<(ETag, u64), ETagError> {
+    assert!(!buffer.is_empty(), "empty buffer");
+    let mut hasher = S3Etagger::<H>::for_known_size(size, threshold, chunksize);
+    let mut len = 0;
+    loop {
+        match reader.read(buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                hasher.update(&buffer[..n]);
+                len += n as u64;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok((hasher.finalize(), len))
+}
+
+/// An adapter that implements [`io::Write`] for an [`ETagHasher`], allowing the hasher to be used
+/// with [`io::copy`] and other writer-based APIs.
+///
+/// Writes to this type never fail and always consume the entire buffer.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug, Default)]
+pub struct WriteHasher<H>(H);
+
+#[cfg(feature = "std")]
+impl<H: ETagHasher> WriteHasher<H> {
+    /// Creates a new adapter wrapping a hasher.
+    pub fn new(hasher: H) -> Self {
+        Self(hasher)
+    }
+
+    /// Returns the result, consuming the adapter.
+    pub fn finalize(self) -> ETag {
+        self.0.finalize()
+    }
+
+    /// Unwraps the adapter, returning the underlying hasher.
+    pub fn into_inner(self) -> H {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<H: ETagHasher> io::Write for WriteHasher<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.update(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An adapter that observes the progress of an [`ETagHasher`], calling `callback` with the
+/// cumulative number of bytes processed.
+///
+/// The callback is invoked once per [`ETagHasher::update`] call rather than per byte, and it does
+/// not affect the computed ETag.
+#[derive(Clone, Debug)]
+pub struct ProgressHasher<H, F> {
+    hasher: H,
+    callback: F,
+    processed: u64,
+}
+
+impl<H: ETagHasher, F: FnMut(u64)> ProgressHasher<H, F> {
+    /// Creates a new adapter wrapping a hasher.
+    pub fn new(hasher: H, callback: F) -> Self {
+        Self {
+            hasher,
+            callback,
+            processed: 0,
+        }
+    }
+
+    /// Unwraps the adapter, returning the underlying hasher.
+    pub fn into_inner(self) -> H {
+        self.hasher
+    }
+}
+
+impl<H: ETagHasher, F: FnMut(u64)> ETagHasher for ProgressHasher<H, F> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        self.hasher.update(data);
+        self.processed += data.len() as u64;
+        (self.callback)(self.processed);
+    }
+
+    fn finalize(self) -> ETag {
+        self.hasher.finalize()
+    }
+}
+
+/// An adapter that compares the ETag computed by an [`ETagHasher`] with an expected value on
+/// finalization.
+///
+/// The comparison is made by [`ETag::ct_eq`], which takes constant time with respect to the digest
+/// bytes. See [`PartVerifier`] to detect a mismatch before the end of the data.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use s3etag::{ETagHasherMulti, VerifyingHasher};
+/// use std::num::NonZeroUsize;
+///
+/// let expected = "393e928fcf5925fcbd3a06aaf20b2d38-3".parse().unwrap();
+/// let inner = ETagHasherMulti::<md5::Md5>::new(NonZeroUsize::new(4).unwrap());
+/// let mut hasher = VerifyingHasher::new(expected, inner);
+/// hasher.update(b"123456789");
+/// let (etag, matched) = hasher.finalize();
+/// assert_eq!(etag, expected);
+/// assert!(matched);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct VerifyingHasher<H> {
+    expected: ETag,
+    hasher: H,
+}
+
+impl<H: ETagHasher> VerifyingHasher<H> {
+    /// Creates a new adapter wrapping a hasher.
+    pub fn new(expected: ETag, hasher: H) -> Self {
+        Self { expected, hasher }
+    }
+
+    /// Returns the expected ETag.
+    pub fn expected(&self) -> ETag {
+        self.expected
+    }
+
+    /// Updates the internal state by processing the data.
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.hasher.update(data);
+    }
+
+    /// Returns the computed ETag and whether it equals the expected one, consuming the adapter.
+    pub fn finalize(self) -> (ETag, bool) {
+        let etag = self.hasher.finalize();
+        (etag, etag.ct_eq(&self.expected))
+    }
+}
+
+/// A hasher state that verifies data against the expected MD5 digests of the parts of a multipart
+/// upload, detecting a mismatch as soon as a diverging part is complete.
+///
+/// The part digests are typically recorded by [`ETagHasherMulti::finalize_with_parts`] from a
+/// known-good copy of the data. Verification stops at the first part whose digest differs from the
+/// expectation or that exceeds the expected part count, so that the rest of a large known-bad input
+/// need not be read.
+///
+/// Early detection is not possible if only the multipart ETag is known, as it is the digest of all
+/// the part digests and cannot be checked until the last part is hashed; compute the ETag and
+/// compare it by [`ETag::verify`] or [`ETag::ct_eq`] in that case.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "md-5")] {
+/// use s3etag::{etag_single, PartVerifier};
+/// use std::num::NonZeroUsize;
+///
+/// let chunksize = NonZeroUsize::new(4).unwrap();
+/// let expected = [b"1234".as_slice(), b"5678", b"9"].map(|p| etag_single::<md5::Md5>(p).digest());
+///
+/// let mut verifier = PartVerifier::<md5::Md5>::new(&expected, chunksize);
+/// assert!(verifier.update(b"12345"));
+/// assert!(verifier.update(b"6789"));
+/// assert!(verifier.finalize());
+///
+/// let mut verifier = PartVerifier::<md5::Md5>::new(&expected, chunksize);
+/// assert!(!verifier.update(b"X2345")); // stops after the first part
+/// assert_eq!(verifier.mismatch(), Some(0));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PartVerifier<'a, H> {
+    expected: &'a [[u8; 16]],
+    chunksize: NonZeroUsize,
+    hasher: H,
+    current_capacity: usize,
+    n_parts: usize,
+    mismatch: Option<usize>,
+}
+
+impl<'a, H: Md5Hasher> PartVerifier<'a, H> {
+    /// Creates a new verifier of data split into `chunksize`-byte parts against the expected
+    /// digests of the parts in order.
+    pub fn new(expected: &'a [[u8; 16]], chunksize: NonZeroUsize) -> Self {
+        Self {
+            expected,
+            chunksize,
+            hasher: Default::default(),
+            current_capacity: chunksize.into(),
+            n_parts: 0,
+            mismatch: None,
+        }
+    }
+
+    /// Updates the internal state by processing the data and returns `false` if a mismatch has
+    /// been detected, after which any data is ignored.
+    pub fn update(&mut self, data: impl AsRef<[u8]>) -> bool {
+        let mut buf = data.as_ref();
+        while self.mismatch.is_none() && !buf.is_empty() {
+            if self.n_parts == self.expected.len() {
+                self.mismatch = Some(self.n_parts);
+                break;
+            }
+            let used = buf.len().min(self.current_capacity);
+            self.hasher.update(&buf[..used]);
+            self.current_capacity -= used;
+            buf = &buf[used..];
+            if self.current_capacity == 0 {
+                self.seal_part();
+            }
+        }
+        self.mismatch.is_none()
+    }
+
+    /// Returns the zero-based index of the first part that has been found not to match, if any.
+    ///
+    /// The index equals the number of expected parts if the data is longer than expected.
+    pub fn mismatch(&self) -> Option<usize> {
+        self.mismatch
+    }
+
+    /// Returns `true` if all the parts match the expected digests, consuming the verifier.
+    ///
+    /// This method checks the last incomplete part and returns `false` if the data is shorter than
+    /// expected.
+    pub fn finalize(mut self) -> bool {
+        if self.mismatch.is_none() && self.current_capacity < self.chunksize.get() {
+            self.seal_part();
+        }
+        self.mismatch.is_none() && self.n_parts == self.expected.len()
+    }
+
+    /// Compares the digest of the current part with the expected one and starts the next part.
+    fn seal_part(&mut self) {
+        let digest: [u8; 16] = self.hasher.finalize_reset().into();
+        if digest != self.expected[self.n_parts] {
+            self.mismatch = Some(self.n_parts);
+        }
+        self.n_parts += 1;
+        self.current_capacity = self.chunksize.into();
+    }
+}
+
+/// The calculated ETag value type.
+///
+/// Two ETags are equal if both the digests and the part counts are equal. Note that a single-part
+/// ETag and a multipart ETag with one part are distinct even if the digests happen to be equal,
+/// just as S3 treats `"<digest>"` and `"<digest>-1"` as different values.
+///
+/// ETags are ordered lexicographically by the digest bytes first and then by the part counts,
+/// where a single-part ETag precedes any multipart ETag with the same digest. The ordering is
+/// only meant for deterministic sorting and carries no meaning such as upload order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ETag {
+    digest: [u8; 16],
+    n_chunks: Option<NonZeroUsize>,
+}
+
+#[cfg(test)]
+mod etag_tests {
+    extern crate std;
+
+    use std::hash::{BuildHasher as _, RandomState};
+
+    use super::ETag;
+
+    /// Checks that ETags hash consistently with equality, distinguishing the part count.
+    #[test]
+    fn hash_consistent_with_eq() {
+        let state = RandomState::new();
+        let parse = |s: &str| s.parse::<ETag>().unwrap();
+        let single = parse("f452076d11f30dfc8658282bf01f27f4");
+        let multi = parse("f452076d11f30dfc8658282bf01f27f4-3");
+        assert_eq!(
+            state.hash_one(single),
+            state.hash_one(parse("F452076D11F30DFC8658282BF01F27F4"))
+        );
+        assert_eq!(
+            state.hash_one(multi),
+            state.hash_one(parse("f452076d11f30dfc8658282bf01f27f4-3"))
+        );
+        assert_ne!(single, multi);
+        assert_ne!(state.hash_one(single), state.hash_one(multi));
+        assert_ne!(
+            state.hash_one(multi),
+            state.hash_one(parse("f452076d11f30dfc8658282bf01f27f4-4"))
+        );
+    }
+}
+
+impl ETag {
+    /// Combines the MD5 digests of the parts, which may be computed separately, into the multipart
+    /// ETag, producing the same result as [`ETagHasherMulti`] fed with the whole data.
+    ///
+    /// An empty slice results in the single-part ETag of the empty input, just as
+    /// [`ETagHasherMulti`] that has not consumed any byte does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "md-5")] {
+    /// use s3etag::{etag_single, ETag};
+    ///
+    /// let parts = [b"1234".as_slice(), b"5678", b"9"].map(|p| etag_single::<md5::Md5>(p).digest());
+    /// let etag = ETag::from_part_digests::<md5::Md5>(&parts);
+    /// assert_eq!(etag.to_string(), "393e928fcf5925fcbd3a06aaf20b2d38-3");
+    /// # }
+    /// ```
+    pub fn from_part_digests<H: Md5Hasher>(digests: &[[u8; 16]]) -> Self {
+        let mut hasher = H::default();
+        for digest in digests {
+            Md5Hasher::update(&mut hasher, digest);
+        }
+        Self {
+            digest: Md5Hasher::finalize(hasher).into(),
+            n_chunks: NonZeroUsize::new(digests.len()),
+        }
+    }
+
+    /// Creates an ETag from the raw MD5 digest bytes and the number of parts, which is `None` for a
+    /// single-part ETag.
+    pub const fn from_raw(digest: [u8; 16], part_count: Option<NonZeroUsize>) -> Self {
+        Self {
+            digest,
+            n_chunks: part_count,
+        }
+    }
+
+    /// Returns the raw MD5 digest bytes.
+    ///
+    /// For a multipart ETag, this is the digest of the concatenated part digests.
+    pub fn digest(&self) -> [u8; 16] {
+        self.digest
+    }
+
+    /// Returns the number of parts if this is a multipart ETag.
+    pub fn part_count(&self) -> Option<NonZeroUsize> {
+        self.n_chunks
+    }
+
+    /// Returns `true` if this is a multipart ETag.
+    pub fn is_multipart(&self) -> bool {
+        self.n_chunks.is_some()
+    }
+
+    /// Returns `true` if this multipart ETag has more parts than [`MAX_PART_COUNT`], which means
+    /// that no object uploaded to S3 can have this ETag.
+    ///
+    /// awscli automatically increases `multipart_chunksize` to keep the number of parts within the
+    /// limit, so such an ETag usually indicates that the chunksize is too small for the file size.
+    pub fn exceeds_part_limit(&self) -> bool {
+        self.n_chunks.is_some_and(|n| n.get() > MAX_PART_COUNT)
+    }
+
+    /// Compares two ETags in constant time with respect to the digest bytes.
+    ///
+    /// Use this method instead of `==` when comparing a computed ETag against an expected value
+    /// supplied by a potentially adversarial party, as the [`PartialEq`] implementation may
+    /// return early at the first mismatching byte. The part counts are compared separately in the
+    /// ordinary variable-time manner because they are not secret in practice.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        let diff = self
+            .digest
+            .iter()
+            .zip(other.digest)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        hint::black_box(diff) == 0 && self.n_chunks == other.n_chunks
+    }
+
+    /// Returns `true` if this ETag is equal to the `expected` string parsed by the
+    /// [`FromStr`](str::FromStr) implementation.
+    ///
+    /// This method returns `false` if `expected` is not a valid ETag string. Optional
+    /// surrounding double quotes are accepted, and the hexadecimal digits are compared regardless
+    /// of the case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "md-5")] {
+    /// let etag = s3etag::etag_single::<md5::Md5>(b"");
+    /// assert!(etag.verify("d41d8cd98f00b204e9800998ecf8427e"));
+    /// assert!(etag.verify("D41D8CD98F00B204E9800998ECF8427E"));
+    /// assert!(etag.verify("\"d41d8CD98F00b204E9800998ecf8427e\""));
+    /// assert!(!etag.verify("d41d8cd98f00b204e9800998ecf8427e-1"));
+    /// # }
+    /// ```
+    pub fn verify(&self, expected: &str) -> bool {
+        expected.parse::<Self>().is_ok_and(|e| e == *self)
+    }
+
+    /// Returns a wrapper that displays this ETag surrounded by double quotes, as in the `ETag`
+    /// header of the S3 REST API responses.
+    pub fn display_quoted(&self) -> DisplayQuoted<'_> {
+        DisplayQuoted(self)
+    }
+
+    /// Returns the digest encoded in base64, followed by `-N` for a multipart ETag of `N` parts.
+    ///
+    /// This is the representation used by the `x-amz-checksum-*` headers, where S3 likewise
+    /// appends the part count after the base64 digest for composite checksums.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn to_base64(&self) -> String {
+        Checksum {
+            digest: self.digest.as_slice().try_into().unwrap(),
+            n_chunks: self.n_chunks,
+        }
+        .to_string()
+    }
+
+    /// Returns the 32 lowercase hexadecimal digits of the digest without the `-N` multipart
+    /// suffix.
+    ///
+    /// For a multipart ETag, this is the hexadecimal MD5 digest of the concatenated part digests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let etag: s3etag::ETag = "393e928fcf5925fcbd3a06aaf20b2d38-3".parse().unwrap();
+    /// assert_eq!(etag.digest_hex(), "393e928fcf5925fcbd3a06aaf20b2d38");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn digest_hex(&self) -> String {
+        use fmt::Write as _;
+        let mut buf = String::with_capacity(32);
+        for e in self.digest {
+            write!(buf, "{:02x}", e).unwrap();
+        }
+        buf
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_hex(f, false)
+    }
+}
+
+/// Formats the ETag with lowercase hexadecimal digits, which is identical to [`fmt::Display`].
+impl fmt::LowerHex for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_hex(f, false)
+    }
+}
+
+/// Formats the ETag with uppercase hexadecimal digits. The multipart suffix is unaffected.
+impl fmt::UpperHex for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_hex(f, true)
+    }
+}
+
+impl ETag {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>, upper: bool) -> fmt::Result {
+        use fmt::Write as _;
+        let mut buf = ArrayString::<64>::new();
+        for e in self.digest {
+            if upper {
+                write!(buf, "{:02X}", e)?;
+            } else {
+                write!(buf, "{:02x}", e)?;
+            }
+        }
+        if let Some(n) = self.n_chunks {
+            write!(buf, "-{}", n)?;
+        }
+        fmt::Display::fmt(buf.as_str(), f)
+    }
+}
+
+/// A helper struct for displaying an [`ETag`] in double quotes, created by
+/// [`ETag::display_quoted`].
+#[derive(Debug)]
+pub struct DisplayQuoted<'a>(&'a ETag);
+
+impl fmt::Display for DisplayQuoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write as _;
+        let mut buf = ArrayString::<64>::new();
+        write!(buf, "\"{}\"", self.0)?;
+        fmt::Display::fmt(buf.as_str(), f)
+    }
+}
+
+impl From<[u8; 16]> for ETag {
+    fn from(digest: [u8; 16]) -> Self {
+        Self {
+            digest,
+            n_chunks: None,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for ETag {
+    type Error = ETagError;
+
+    /// Interprets a slice of 16 raw digest bytes as a single-part ETag.
+    fn try_from(digest: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; 16]>::try_from(digest)
+            .map(Self::from)
+            .map_err(|_| ParseETagError::InvalidLength.into())
+    }
+}
+
+impl str::FromStr for ETag {
+    type Err = ETagError;
+
+    /// Parses an ETag string in the form of 32 hexadecimal digits optionally followed by a `-N`
+    /// multipart suffix.
+    ///
+    /// The digits are case-insensitive, although S3 returns them in lowercase. A pair of
+    /// surrounding double quotes, as returned by the S3 REST API, is stripped before parsing.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_etag(s)?)
+    }
+}
+
+/// Parses an ETag string for the [`FromStr`](str::FromStr) implementation of [`ETag`].
+fn parse_etag(s: &str) -> Result<ETag, ParseETagError> {
+    let s = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    let (hex, n_chunks) = match s.split_once('-') {
+        None => (s, None),
+        Some((hex, n)) => {
+            if !n.bytes().all(|c| c.is_ascii_digit()) {
+                return Err(ParseETagError::InvalidPartCount);
+            }
+            match n.parse::<NonZeroUsize>() {
+                Ok(n) => (hex, Some(n)),
+                Err(_) => return Err(ParseETagError::InvalidPartCount),
+            }
+        }
+    };
+
+    if hex.len() != 32 {
+        return Err(ParseETagError::InvalidLength);
+    }
+    let mut digest = [0u8; 16];
+    for (dst, src) in digest.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+        fn decode(c: u8) -> Result<u8, ParseETagError> {
+            match c {
+                b'0'..=b'9' => Ok(c - b'0'),
+                b'a'..=b'f' => Ok(c - b'a' + 10),
+                b'A'..=b'F' => Ok(c - b'A' + 10),
+                _ => Err(ParseETagError::InvalidDigit),
+            }
+        }
+        *dst = decode(src[0])? << 4 | decode(src[1])?;
+    }
+    Ok(ETag { digest, n_chunks })
+}
+
+/// The cause of a failure to parse an [`ETag`] from a string or to convert it from a byte slice,
+/// which is returned as [`ETagError::ParseETag`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseETagError {
+    /// The digest part does not consist of exactly 32 characters, or the byte slice does not
+    /// consist of exactly 16 bytes.
+    InvalidLength,
+    /// The digest part contains a character other than hexadecimal digits.
+    InvalidDigit,
+    /// The multipart suffix is not a positive decimal integer that fits in `usize`.
+    InvalidPartCount,
+}
+
+impl fmt::Display for ParseETagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidLength => "invalid digest length",
+            Self::InvalidDigit => "invalid hexadecimal digit",
+            Self::InvalidPartCount => "invalid part count",
+        })
+    }
+}
+
+impl error::Error for ParseETagError {}
+
+/// The calculated additional checksum value type.
+///
+/// This type is formatted in the base64 encoding followed by `-N` for a composite checksum of `N`
+/// parts, as S3 returns it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    digest: ArrayVec<u8, { Checksum::MAX_LEN }>,
+    n_chunks: Option<NonZeroUsize>,
+}
+
+impl Checksum {
+    /// The maximum length of the digest in bytes.
+    pub const MAX_LEN: usize = 32;
+
+    /// Returns the digest.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Returns the number of parts if this is a composite checksum.
+    pub fn part_count(&self) -> Option<NonZeroUsize> {
+        self.n_chunks
+    }
+
+    /// Returns `true` if this is a composite checksum.
+    pub fn is_multipart(&self) -> bool {
+        self.n_chunks.is_some()
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write as _;
+        const DIGITS: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut buf = ArrayString::<64>::new();
+        for chunk in self.digest.chunks(3) {
+            let n =
+                chunk.iter().fold(0u32, |acc, &e| acc << 8 | e as u32) << (8 * (3 - chunk.len()));
+            for i in 0..4 {
+                buf.push(if i <= chunk.len() {
+                    DIGITS[(n >> (18 - 6 * i) & 0x3f) as usize] as char
+                } else {
+                    '='
+                });
+            }
+        }
+        if let Some(n) = self.n_chunks {
+            write!(buf, "-{}", n)?;
+        }
+        fmt::Display::fmt(buf.as_str(), f)
+    }
+}
+
+/// Parses a size string in bytes or with a size suffix `KB`, `MB`, `GB`, or `TB`, as accepted by
+/// awscli's `multipart_threshold` and `multipart_chunksize` settings.
+///
+/// The suffixes represent powers of 1024 as in awscli. The IEC suffixes `KiB`, `MiB`, `GiB`, and
+/// `TiB` are also accepted with the same meanings. The suffixes are case-insensitive, as in
+/// awscli.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(s3etag::parse_size("8MB").unwrap(), 8 * 1024 * 1024);
+/// assert_eq!(s3etag::parse_size("16mb").unwrap(), 16 * 1024 * 1024);
+/// assert_eq!(s3etag::parse_size("1Gib").unwrap(), 1024 * 1024 * 1024);
+/// assert_eq!(s3etag::parse_size("5Kb").unwrap(), 5 * 1024);
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, ETagError> {
+    parse_size_with_units(s, SizeUnits::Binary)
+}
+
+/// Parses a size string like [`parse_size`] but interprets the suffixes `KB`, `MB`, `GB`, and `TB`
+/// as specified by `units`.
+///
+/// The IEC suffixes `KiB`, `MiB`, `GiB`, and `TiB` always represent powers of 1024. Note that
+/// awscli interprets all the suffixes as powers of 1024, so [`SizeUnits::Binary`] is the only
+/// choice to reproduce the ETags of objects uploaded by awscli.
+pub fn parse_size_with_units(s: &str, units: SizeUnits) -> Result<u64, ETagError> {
+    let (num, suffix) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    if num.is_empty() {
+        return Err(ParseSizeError::InvalidNumber.into());
+    }
+    const SUFFIXES: [(&str, &str); 4] =
+        [("KB", "KiB"), ("MB", "MiB"), ("GB", "GiB"), ("TB", "TiB")];
+    let exp = if suffix.is_empty() {
+        0
+    } else {
+        SUFFIXES
+            .iter()
+            .position(|(si, iec)| {
+                suffix.eq_ignore_ascii_case(si) || suffix.eq_ignore_ascii_case(iec)
+            })
+            .ok_or(ParseSizeError::UnknownSuffix)? as u32
+            + 1
+    };
+    // Only the two-letter suffixes are affected by the units.
+    let base: u64 = match units {
+        SizeUnits::Decimal if suffix.len() == 2 => 1000,
+        _ => 1024,
+    };
+    num.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(base.pow(exp)))
+        .ok_or(ParseSizeError::TooLarge.into())
+}
+
+/// The interpretation of the size suffixes `KB`, `MB`, `GB`, and `TB`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizeUnits {
+    /// Powers of 1024, as in awscli.
+    #[default]
+    Binary,
+    /// Powers of 1000.
+    Decimal,
+}
+
+/// The cause of a failure of [`parse_size`], which is returned as [`ETagError::ParseSize`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseSizeError {
+    /// The string does not start with a decimal integer.
+    InvalidNumber,
+    /// The size suffix is not recognized.
+    UnknownSuffix,
+    /// The size does not fit in `u64`.
+    TooLarge,
+}
+
+impl fmt::Display for ParseSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidNumber => "cannot parse integer",
+            Self::UnknownSuffix => "unknown size suffix",
+            Self::TooLarge => "too large size",
+        })
+    }
+}
+
+impl error::Error for ParseSizeError {}
+
+/// An error type that unifies the errors returned by the fallible functions of this crate.
+///
+/// The parsing functions, the file reading functions, and the fallible methods of `OpensslMd5`
+/// return this type. [`io::Error`] and OpenSSL's error stack convert into it, and it converts into
+/// [`io::Error`], so that it can be propagated with the `?` operator from code that combines them.
+///
+/// The variants depend on the enabled features, so this type is non-exhaustive.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ETagError {
+    /// Reading the data failed.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    Io(io::Error),
+    /// An OpenSSL function failed.
+    #[cfg(feature = "openssl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openssl")))]
+    Openssl(openssl::error::ErrorStack),
+    /// An ETag string could not be parsed.
+    ParseETag(ParseETagError),
+    /// A size string could not be parsed.
+    ParseSize(ParseSizeError),
+}
+
+impl fmt::Display for ETagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "cannot read data: {}", e),
+            #[cfg(feature = "openssl")]
+            Self::Openssl(e) => write!(f, "openssl error: {}", e),
+            Self::ParseETag(e) => write!(f, "cannot parse ETag: {}", e),
+            Self::ParseSize(e) => write!(f, "cannot parse size: {}", e),
+        }
+    }
+}
+
+impl error::Error for ETagError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "openssl")]
+            Self::Openssl(e) => Some(e),
+            Self::ParseETag(e) => Some(e),
+            Self::ParseSize(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for ETagError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl From<openssl::error::ErrorStack> for ETagError {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        Self::Openssl(e)
+    }
+}
+
+impl From<ParseETagError> for ETagError {
+    fn from(e: ParseETagError) -> Self {
+        Self::ParseETag(e)
+    }
+}
+
+impl From<ParseSizeError> for ETagError {
+    fn from(e: ParseSizeError) -> Self {
+        Self::ParseSize(e)
+    }
+}
+
+/// Converts the error into [`io::Error`], unwrapping [`ETagError::Io`] and reporting the other
+/// errors as [`io::ErrorKind::InvalidData`] or [`io::ErrorKind::Other`].
+#[cfg(feature = "std")]
+impl From<ETagError> for io::Error {
+    fn from(e: ETagError) -> Self {
+        match e {
+            ETagError::Io(e) => e,
+            #[cfg(feature = "openssl")]
+            ETagError::Openssl(e) => io::Error::other(e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+mod serde_impls {
+    use core::fmt;
+
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::ETag;
+
+    /// Serializes an ETag as its canonical string form.
+    impl Serialize for ETag {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    /// Deserializes an ETag from a string using the [`FromStr`](core::str::FromStr) logic.
+    impl<'de> Deserialize<'de> for ETag {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct ETagVisitor;
+
+            impl de::Visitor<'_> for ETagVisitor {
+                type Value = ETag;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("an ETag string")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                    v.parse().map_err(E::custom)
+                }
+            }
+
+            deserializer.deserialize_str(ETagVisitor)
+        }
+    }
+}
+
+#[cfg(feature = "md-5")]
+#[cfg_attr(docsrs, doc(cfg(feature = "md-5")))]
+impl Md5Hasher for md5::Md5 {
+    type Output = md5::digest::Output<Self>;
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        md5::Digest::update(self, data)
+    }
+
+    fn finalize(self) -> Self::Output {
+        md5::Digest::finalize(self)
+    }
+
+    fn finalize_reset(&mut self) -> Self::Output {
+        md5::Digest::finalize_reset(self)
+    }
+}
+
+#[cfg(feature = "digest")]
+pub use digest_bindings::DigestMd5;
+
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+mod digest_bindings {
+    use digest::{consts::U16, Digest, FixedOutputReset, OutputSizeUser};
+
+    use super::Md5Hasher;
+
+    /// A wrapper to implement [`Md5Hasher`] for any [`Digest`] implementation producing 16-byte
+    /// output.
+    ///
+    /// This type allows an alternative MD5 implementation that this crate does not know about to
+    /// be plugged into [`ETagHasherMulti`](super::ETagHasherMulti) and other hashers.
+    #[derive(Clone, Debug, Default)]
+    pub struct DigestMd5<D>(pub D);
+
+    impl<D> Md5Hasher for DigestMd5<D>
+    where
+        D: Digest + FixedOutputReset + OutputSizeUser<OutputSize = U16> + Default,
+    {
+        type Output = digest::Output<D>;
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            Digest::update(&mut self.0, data)
+        }
+
+        fn finalize(self) -> Self::Output {
+            Digest::finalize(self.0)
+        }
+
+        fn finalize_reset(&mut self) -> Self::Output {
+            Digest::finalize_reset(&mut self.0)
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+pub use openssl_bindings::OpensslMd5;
+
+#[cfg(feature = "openssl")]
+#[cfg_attr(docsrs, doc(cfg(feature = "openssl")))]
+mod openssl_bindings {
+    use openssl::{md::Md, md_ctx::MdCtx};
+
+    use super::{ETagError, Md5Hasher};
+
+    /// A wrapper for OpenSSL's `EVP_MD_CTX` object to implement [`Md5Hasher`].
+    ///
+    /// # Panics
+    ///
+    /// The [`Default`] and [`Md5Hasher`] implementations panic if the underlying OpenSSL functions
+    /// (`EVP_MD_CTX_new`, `EVP_DigestInit_ex`, `EVP_DigestUpdate`, or `EVP_DigestFinal_ex`)
+    /// return an error, which usually indicates memory exhaustion or a broken OpenSSL
+    /// installation. Use [`OpensslMd5::try_new`], [`OpensslMd5::try_update`], and
+    /// [`OpensslMd5::try_finalize`] to handle such errors instead.
+    pub struct OpensslMd5(MdCtx);
+
+    impl OpensslMd5 {
+        /// Creates a new hasher, returning an error if OpenSSL fails to initialize the context.
+        pub fn try_new() -> Result<Self, ETagError> {
+            let mut ctx = MdCtx::new()?;
+            ctx.digest_init(Md::md5())?;
+            Ok(Self(ctx))
+        }
+
+        /// Updates the internal state by processing the data, returning an error if OpenSSL
+        /// fails.
+        pub fn try_update(&mut self, data: impl AsRef<[u8]>) -> Result<(), ETagError> {
+            Ok(self.0.digest_update(data.as_ref())?)
+        }
+
+        /// Returns the result, consuming the hasher, or an error if OpenSSL fails.
+        pub fn try_finalize(mut self) -> Result<[u8; 16], ETagError> {
+            let mut buffer = [0; 16];
+            self.0.digest_final(&mut buffer)?;
+            Ok(buffer)
+        }
+    }
+
+    impl Default for OpensslMd5 {
+        fn default() -> Self {
+            Self::try_new().expect("openssl error")
+        }
+    }
+
+    impl Md5Hasher for OpensslMd5 {
+        type Output = [u8; 16];
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            self.try_update(data).expect("openssl error");
+        }
+
+        fn finalize(self) -> Self::Output {
+            self.try_finalize().expect("openssl error")
+        }
+
+        fn finalize_reset(&mut self) -> Self::Output {
+            let mut buffer = [0; 16];
+            self.0.digest_final(&mut buffer).expect("openssl error");
+            self.0.digest_init(Md::md5()).expect("openssl error");
+            buffer
+        }
+    }
+}
+
+#[cfg(feature = "sha1")]
+pub use sha1_bindings::Sha1Hasher;
+
+#[cfg(feature = "sha1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sha1")))]
+mod sha1_bindings {
+    use sha1::{Digest, Sha1};
+
+    use super::{ChecksumHasher, CompositeHasher};
+
+    /// A hasher state for the SHA-1 additional checksum (`x-amz-checksum-sha1`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use core::num::NonZeroUsize;
+    /// use s3etag::Sha1Hasher;
+    ///
+    /// let mut hasher = Sha1Hasher::full_object();
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "98O8HYCOBHMq32eZZczDTKeuNEE=");
+    ///
+    /// let mut hasher = Sha1Hasher::composite(NonZeroUsize::new(4).unwrap());
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "dTcKdjYm1TzQORJOtCI1ajTa40w=-3");
+    /// ```
+    pub type Sha1Hasher = CompositeHasher<Sha1>;
+
+    impl ChecksumHasher for Sha1 {
+        const OUTPUT_LEN: usize = 20;
+        type Output = sha1::digest::Output<Sha1>;
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            Digest::update(self, data)
+        }
+
+        fn finalize(self) -> Self::Output {
+            Digest::finalize(self)
+        }
+
+        fn finalize_reset(&mut self) -> Self::Output {
+            Digest::finalize_reset(self)
+        }
+    }
+}
+
+#[cfg(feature = "sha2")]
+pub use sha2_bindings::Sha256Hasher;
+
+#[cfg(feature = "sha2")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sha2")))]
+mod sha2_bindings {
+    use sha2::{Digest, Sha256};
+
+    use super::{ChecksumHasher, CompositeHasher};
+
+    /// A hasher state for the SHA-256 additional checksum (`x-amz-checksum-sha256`).
+    pub type Sha256Hasher = CompositeHasher<Sha256>;
+
+    impl ChecksumHasher for Sha256 {
+        const OUTPUT_LEN: usize = 32;
+
+        type Output = sha2::digest::Output<Sha256>;
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            Digest::update(self, data)
+        }
+
+        fn finalize(self) -> Self::Output {
+            Digest::finalize(self)
+        }
+
+        fn finalize_reset(&mut self) -> Self::Output {
+            Digest::finalize_reset(self)
+        }
+    }
+}
+
+#[cfg(feature = "crc32fast")]
+pub use crc32fast_bindings::{Crc32, Crc32Hasher};
+
+#[cfg(feature = "crc32fast")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc32fast")))]
+mod crc32fast_bindings {
+    use super::{ChecksumHasher, CompositeHasher};
+
+    /// A hasher state for the CRC32 additional checksum (`x-amz-checksum-crc32`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use core::num::NonZeroUsize;
+    /// use s3etag::Crc32Hasher;
+    ///
+    /// let mut hasher = Crc32Hasher::full_object();
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "y/Q5Jg==");
+    ///
+    /// let mut hasher = Crc32Hasher::composite(NonZeroUsize::new(4).unwrap());
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "+vEo6Q==-3");
+    /// ```
+    pub type Crc32Hasher = CompositeHasher<Crc32>;
+
+    /// A wrapper for [`crc32fast::Hasher`] to implement [`ChecksumHasher`] with the big-endian
+    /// output used by S3.
+    #[derive(Clone, Debug, Default)]
+    pub struct Crc32(crc32fast::Hasher);
+
+    impl ChecksumHasher for Crc32 {
+        const OUTPUT_LEN: usize = 4;
+
+        type Output = [u8; 4];
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            self.0.update(data.as_ref())
+        }
+
+        fn finalize(self) -> Self::Output {
+            self.0.finalize().to_be_bytes()
+        }
+    }
+}
+
+#[cfg(feature = "crc32c")]
+pub use crc32c_bindings::{Crc32c, Crc32cHasher};
+
+#[cfg(feature = "crc32c")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc32c")))]
+mod crc32c_bindings {
+    use super::{ChecksumHasher, CompositeHasher};
+
+    /// A hasher state for the CRC32C additional checksum (`x-amz-checksum-crc32c`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use core::num::NonZeroUsize;
+    /// use s3etag::Crc32cHasher;
+    ///
+    /// let mut hasher = Crc32cHasher::full_object();
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "4waSgw==");
+    ///
+    /// let mut hasher = Crc32cHasher::composite(NonZeroUsize::new(4).unwrap());
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "uSVqcA==-3");
+    /// ```
+    pub type Crc32cHasher = CompositeHasher<Crc32c>;
+
+    /// A CRC32C (Castagnoli) state built on [`crc32c::crc32c_append`] to implement
+    /// [`ChecksumHasher`] with the big-endian output used by S3.
+    #[derive(Clone, Debug, Default)]
+    pub struct Crc32c(u32);
+
+    impl ChecksumHasher for Crc32c {
+        const OUTPUT_LEN: usize = 4;
+
+        type Output = [u8; 4];
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            self.0 = crc32c::crc32c_append(self.0, data.as_ref());
+        }
+
+        fn finalize(self) -> Self::Output {
+            self.0.to_be_bytes()
+        }
+    }
+}
+
+#[cfg(feature = "crc64fast-nvme")]
+pub use crc64fast_nvme_bindings::{Crc64Nvme, Crc64NvmeHasher};
+
+#[cfg(feature = "crc64fast-nvme")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc64fast-nvme")))]
+mod crc64fast_nvme_bindings {
+    use core::fmt;
+
+    use super::{ChecksumHasher, FullObjectHasher};
+
+    /// A hasher state for the CRC64NVME full-object checksum (`x-amz-checksum-crc64nvme`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use core::num::NonZeroUsize;
+    /// use s3etag::{CompositeHasher, Crc64Nvme, Crc64NvmeHasher};
+    ///
+    /// let mut hasher = Crc64NvmeHasher::new();
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "rosUhgp5mIg=");
+    ///
+    /// // S3 never uses the composite construction for CRC64NVME, which is still available.
+    /// let mut hasher = CompositeHasher::<Crc64Nvme>::composite(NonZeroUsize::new(4).unwrap());
+    /// hasher.update(b"123456789");
+    /// assert_eq!(hasher.finalize().to_string(), "8D5ZV5ck59E=-3");
+    /// ```
+    pub type Crc64NvmeHasher = FullObjectHasher<Crc64Nvme>;
+
+    /// A wrapper for [`crc64fast_nvme::Digest`] to implement [`ChecksumHasher`] with the
+    /// big-endian output used by S3.
+    #[derive(Clone, Default)]
+    pub struct Crc64Nvme(crc64fast_nvme::Digest);
+
+    impl ChecksumHasher for Crc64Nvme {
+        const OUTPUT_LEN: usize = 8;
+
+        type Output = [u8; 8];
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            self.0.write(data.as_ref())
+        }
+
+        fn finalize(self) -> Self::Output {
+            self.0.sum64().to_be_bytes()
+        }
+    }
+
+    /// Formats the checksum of the data processed so far, as [`crc64fast_nvme::Digest`] does not
+    /// implement [`Debug`](fmt::Debug).
+    impl fmt::Debug for Crc64Nvme {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("Crc64Nvme")
+                .field(&format_args!("{:#018x}", self.0.sum64()))
+                .finish()
+        }
+    }
+}
+
+#[cfg(feature = "aws-sdk")]
+pub use aws_sdk_bindings::{verify_object, VerifyObjectError};
+
+#[cfg(feature = "aws-sdk")]
+#[cfg_attr(docsrs, doc(cfg(feature = "aws-sdk")))]
+mod aws_sdk_bindings {
+    use std::num::{NonZeroU64, NonZeroUsize};
+    use std::{error, fmt, io, path};
+
+    use aws_sdk_s3::types::{ChecksumMode, ServerSideEncryption};
+    use tokio::io::AsyncReadExt as _;
+
+    use super::{ETag, Md5Hasher};
+
+    /// Verifies that a local file has the same content as an S3 object.
+    ///
+    /// This function issues a `HeadObject` request and compares the ETag of the object with that
+    /// of the file computed for `multipart_threshold` and `multipart_chunksize` values. Since the
+    /// ETag of an object encrypted with SSE-KMS or SSE-C is not an MD5 digest, this function
+    /// compares the additional checksum of such an object instead if the algorithm is enabled by
+    /// a crate feature, and it returns [`VerifyObjectError::Unverifiable`] otherwise.
+    pub async fn verify_object<H: Md5Hasher>(
+        client: &aws_sdk_s3::Client,
+        bucket: &str,
+        key: &str,
+        path: impl AsRef<path::Path>,
+        threshold: NonZeroU64,
+        chunksize: NonZeroUsize,
+    ) -> Result<bool, VerifyObjectError> {
+        let path = path.as_ref();
+        let head = client
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .checksum_mode(ChecksumMode::Enabled)
+            .send()
+            .await
+            .map_err(|e| VerifyObjectError::Request(Box::new(e)))?;
+
+        let is_md5_etag = head.sse_customer_algorithm().is_none()
+            && !matches!(
+                head.server_side_encryption(),
+                Some(ServerSideEncryption::AwsKms | ServerSideEncryption::AwsKmsDsse)
+            );
+        if is_md5_etag {
+            let expected = head
+                .e_tag()
+                .and_then(|e| e.parse::<ETag>().ok())
+                .ok_or(VerifyObjectError::InvalidETag)?;
+            let file = tokio::fs::File::open(path).await?;
+            let etag = super::etag_from_async_read::<H, _>(file, threshold, chunksize).await?;
+            return Ok(etag == expected);
+        }
+
+        #[cfg(feature = "crc64fast-nvme")]
+        if let Some(expected) = head.checksum_crc64_nvme() {
+            let mut hasher = super::Crc64NvmeHasher::new();
+            read_file(path, |data| hasher.update(data)).await?;
+            return Ok(hasher.finalize().to_string() == expected);
+        }
+        #[cfg(feature = "crc32c")]
+        if let Some(expected) = head.checksum_crc32_c() {
+            let mut hasher = super::Crc32cHasher::new(threshold, chunksize);
+            read_file(path, |data| hasher.update(data)).await?;
+            return Ok(hasher.finalize().to_string() == expected);
+        }
+        #[cfg(feature = "crc32fast")]
+        if let Some(expected) = head.checksum_crc32() {
+            let mut hasher = super::Crc32Hasher::new(threshold, chunksize);
+            read_file(path, |data| hasher.update(data)).await?;
+            return Ok(hasher.finalize().to_string() == expected);
+        }
+        #[cfg(feature = "sha1")]
+        if let Some(expected) = head.checksum_sha1() {
+            let mut hasher = super::Sha1Hasher::new(threshold, chunksize);
+            read_file(path, |data| hasher.update(data)).await?;
+            return Ok(hasher.finalize().to_string() == expected);
+        }
+        #[cfg(feature = "sha2")]
+        if let Some(expected) = head.checksum_sha256() {
+            let mut hasher = super::Sha256Hasher::new(threshold, chunksize);
+            read_file(path, |data| hasher.update(data)).await?;
+            return Ok(hasher.finalize().to_string() == expected);
+        }
+        Err(VerifyObjectError::Unverifiable)
+    }
+
+    /// Feeds the entire content of a file to `update`.
+    #[cfg_attr(
+        not(any(
+            feature = "crc32fast",
+            feature = "crc32c",
+            feature = "crc64fast-nvme",
+            feature = "sha1",
+            feature = "sha2"
+        )),
+        allow(dead_code)
+    )]
+    async fn read_file(path: &path::Path, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buffer = vec![0u8; 64 * 1024].into_boxed_slice();
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break Ok(()),
+                Ok(n) => update(&buffer[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => break Err(e),
+            }
+        }
+    }
+
+    /// An error verifying a local file against an S3 object.
+    #[derive(Debug)]
+    pub enum VerifyObjectError {
+        /// The `HeadObject` request failed.
+        Request(Box<dyn error::Error + Send + Sync>),
+        /// The local file could not be read.
+        Io(io::Error),
+        /// The response had no valid ETag.
+        InvalidETag,
+        /// The object had neither an MD5 ETag nor an additional checksum in an enabled algorithm.
+        Unverifiable,
+    }
+
+    impl fmt::Display for VerifyObjectError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::Request(e) => write!(f, "HeadObject request failed: {}", e),
+                Self::Io(e) => write!(f, "cannot read file: {}", e),
+                Self::InvalidETag => f.write_str("invalid ETag in response"),
+                Self::Unverifiable => f.write_str("no verifiable ETag or checksum in response"),
+            }
+        }
+    }
+
+    impl error::Error for VerifyObjectError {
+        fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+            match self {
+                Self::Request(e) => Some(e.as_ref()),
+                Self::Io(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<io::Error> for VerifyObjectError {
+        fn from(e: io::Error) -> Self {
+            Self::Io(e)
         }
     }
 }
@@ -1,18 +1,99 @@
 use std::num::{NonZeroU64, NonZeroUsize};
-use std::{error, fs, io, path, process};
+use std::{collections, env, error, fmt, fs, io, path, process, sync, thread, time};
 
-#[cfg(unix)]
-use std::os::unix::ffi::OsStrExt as _;
+use s3etag::{Checksum, ChecksumHasher, CompositeHasher, ETag, ETagHasher, S3Etagger, SizeUnits};
 
-use s3etag::{ETag, ETagHasher, ETagHasherMulti};
+mod check;
+mod detect;
+#[cfg(feature = "flate2")]
+mod gunzip;
+mod interrupt;
+mod output;
+mod profile;
+mod progress;
+mod retry;
+mod tee;
+mod walk;
 
 fn main() -> process::ExitCode {
     const PROG: &str = env!("CARGO_PKG_NAME");
-    const THRESHOLD_HELP: &str =
-        "multipart_threshold used for upload in bytes or with a size suffix KB, MB, GB, or TB";
-    const CHUNKSIZE_HELP: &str =
-        "multipart_chunksize used for upload in bytes or with a size suffix KB, MB, GB, or TB";
-    let matches = clap::Command::new(PROG)
+    const THRESHOLD_HELP: &str = "multipart_threshold used for upload in bytes or with a size \
+        suffix KB, MB, GB, or TB, which are powers of 1024 as in awscli unless --units is decimal \
+        (KiB, MiB, GiB, and TiB are also accepted)";
+    const CHUNKSIZE_HELP: &str = "multipart_chunksize used for upload in bytes or with a size \
+        suffix KB, MB, GB, or TB, which are powers of 1024 as in awscli unless --units is decimal \
+        (KiB, MiB, GiB, and TiB are also accepted)";
+    const FORCE_SINGLE_HELP: &str = "compute single-part ETags, i.e., the plain MD5 digests, \
+        regardless of --threshold";
+    const FORCE_MULTIPART_HELP: &str = "compute multipart ETags even for FILEs smaller than \
+        --threshold, as a threshold of one byte would, since awscli rejects zero; empty FILEs \
+        still result in the single-part ETag as on S3";
+    const UNITS_HELP: &str = "interpretation of the size suffixes KB, MB, GB, and TB; awscli \
+        always uses binary powers of 1024, so only the default reproduces its ETags, and the sizes \
+        read by --profile are always binary";
+    const AUTO_CHUNKSIZE_HELP: &str = "adjust chunksize as awscli does: double it until a file \
+        fits in 10,000 parts and limit it between 5MB and 5GB";
+    const FOLLOW_SYMLINKS_HELP: &str = "follow symbolic links found in directories in the \
+        recursive mode, which are skipped by default; symbolic links given as FILEs are always \
+        followed";
+    const EXCLUDE_HELP: &str = "skip files and directories matching PATTERN in the recursive \
+        mode; PATTERN without `/` matches file names at any depth, and PATTERN with `/` matches \
+        paths relative to the directory FILE, where `**` matches any directories; may be repeated";
+    const DEDUP_INODES_HELP: &str = "hash each file only once even if it is reached through \
+        multiple hard links, printing its ETag for every path; ignored on platforms without inode \
+        numbers";
+    const CONTENT_MD5_HELP: &str = "print the base64 MD5 digest of each FILE for the Content-MD5 \
+        header of a single-part upload, reporting an error for a FILE that would be uploaded in \
+        multiple parts";
+    const RETRIES_HELP: &str = "retry a read of a FILE that fails with a transient error, i.e., \
+        EIO or a timeout as reported by network filesystems, up to N consecutive times with \
+        exponential backoff from 100ms, resuming from the last offset read; the standard input \
+        and --mmap are not retried";
+    const STDIN_SIZE_HELP: &str = "length of the standard input in bytes, which selects the \
+        single-part or multipart mode immediately instead of buffering up to --threshold bytes; \
+        accepts the size suffixes as well, and a stream of a different length is reported as \
+        --strict specifies";
+    const BOTH_HELP: &str = "print each ETag in hexadecimal digits and in base64 side by side \
+        for inspection, which is a text layout for humans rather than another machine format";
+    const HEADER_HELP: &str = "print a comment line recording the version, threshold, \
+        chunksize, and algorithm before the ETags, which --check reads to apply the same \
+        settings to the following lines";
+    const COMBINE_HELP: &str = "print only a single value combining the ETags of all FILEs, \
+        which is the MD5 digest of the sorted list of ETags in lines (not an S3 ETag)";
+    const CONCAT_HELP: &str = "print a single ETag of the concatenation of FILEs in the given \
+        order as `-`, as if it were uploaded as one object; nothing is printed if any FILE fails";
+    #[cfg(feature = "flate2")]
+    const GUNZIP_HELP: &str = "decompress each gzip FILE before hashing to compute the ETag of \
+        the uncompressed content, which is copied by --tee as well";
+    const RANGE_HELP: &str = "print the plain MD5 digest of the bytes from START up to but not \
+        including END of each FILE, which equals the digest of the part at that position of a \
+        multipart upload; START and END accept the size suffixes as well";
+    const FORMAT_HELP: &str = "output format; `jsonl` prints a JSON object per line as soon as \
+        each FILE is hashed, and `json` and `jsonl` convert non-UTF-8 filenames lossily; records \
+        are printed in the order of FILEs even with --jobs";
+    const OUTPUT_HELP: &str = "write the records to FILE instead of the standard output, \
+        keeping the progress, warnings, and errors on the standard error; FILE must not be one of \
+        the input FILEs, and the files found in directories that turn out to be FILE are skipped \
+        with a warning";
+    const STATS_HELP: &str = "print the numbers of files and bytes processed and the elapsed \
+        time on the standard error at the end";
+    const PROFILE_HELP: &str = "use multipart_threshold and multipart_chunksize in the s3 \
+        settings of the named profile in the AWS config file unless --threshold or --chunksize \
+        is specified";
+    const CHECK_HELP: &str = "read ETags from FILEs and check them; the same threshold and \
+        chunksize as used to generate FILEs must be specified to check multipart ETags";
+    const COLOR_HELP: &str = "highlight the results of --check; `auto` does so if the standard \
+        output is a terminal and NO_COLOR is not set";
+    const ALGORITHM_HELP: &str = "checksum algorithm; algorithms other than md5 compute S3's \
+        additional checksums, which are printed in base64, and are accepted in any case as the \
+        values of --checksum-algorithm of awscli";
+    const EXPLAIN_HELP: &str = "print the offsets and lengths of the parts into which each FILE \
+        would be split for upload without reading it";
+    const MATCH_ETAG_HELP: &str = "search for the chunksize that reproduces a multipart ETAG \
+        for each FILE from every chunksize that gives the part count if the FILE is small enough \
+        to hash for each of them within 1GiB in total, or otherwise from the powers of two from \
+        1MiB to 4GiB and the smallest chunksizes that give the part count";
+    let command = clap::Command::new(PROG)
         .version(env!("CARGO_PKG_VERSION"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .arg(
@@ -21,13 +102,13 @@ fn main() -> process::ExitCode {
                 .value_name("FILE")
                 .value_parser(clap::value_parser!(path::PathBuf))
                 .action(clap::ArgAction::Append)
-                .help("filenames"),
+                .help("filenames; `-` means the standard input"),
         )
         .arg(
             clap::Arg::new("threshold")
                 .long("threshold")
                 .value_name("SIZE")
-                .value_parser(parse_threshold)
+                .value_parser(|s: &str| parse_threshold(s, SizeUnits::Binary))
                 .env("S3ETAG_THRESHOLD")
                 .default_value("8MB")
                 .help(THRESHOLD_HELP),
@@ -36,87 +117,817 @@ fn main() -> process::ExitCode {
             clap::Arg::new("chunksize")
                 .long("chunksize")
                 .value_name("SIZE")
-                .value_parser(parse_chunksize)
+                .value_parser(|s: &str| parse_chunksize(s, SizeUnits::Binary))
                 .env("S3ETAG_CHUNKSIZE")
                 .default_value("8MB")
                 .help(CHUNKSIZE_HELP),
         )
-        .get_matches();
+        .arg(
+            clap::Arg::new("force_single")
+                .long("force-single")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("match_etag")
+                .help(FORCE_SINGLE_HELP),
+        )
+        .arg(
+            clap::Arg::new("force_multipart")
+                .long("force-multipart")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["force_single", "match_etag"])
+                .help(FORCE_MULTIPART_HELP),
+        )
+        .arg(
+            clap::Arg::new("units")
+                .long("units")
+                .value_name("UNITS")
+                .value_parser(["binary", "decimal"])
+                .default_value("binary")
+                .help(UNITS_HELP),
+        )
+        .arg(
+            clap::Arg::new("auto_chunksize")
+                .long("auto-chunksize")
+                .action(clap::ArgAction::SetTrue)
+                .help(AUTO_CHUNKSIZE_HELP),
+        )
+        .arg(
+            clap::Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .env("AWS_PROFILE")
+                .help(PROFILE_HELP),
+        )
+        .arg(
+            clap::Arg::new("algorithm")
+                .long("algorithm")
+                .visible_alias("checksum-algorithm")
+                .value_name("ALGORITHM")
+                .value_parser(AlgorithmParser)
+                .default_value("md5")
+                .help(ALGORITHM_HELP),
+        )
+        .arg(
+            clap::Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .action(clap::ArgAction::SetTrue)
+                .help("process regular files in directories recursively"),
+        )
+        .arg(
+            clap::Arg::new("follow_symlinks")
+                .short('L')
+                .long("follow-symlinks")
+                .action(clap::ArgAction::SetTrue)
+                .help(FOLLOW_SYMLINKS_HELP),
+        )
+        .arg(
+            clap::Arg::new("exclude")
+                .long("exclude")
+                .value_name("PATTERN")
+                .value_parser(|s: &str| glob::Pattern::new(s))
+                .action(clap::ArgAction::Append)
+                .requires("recursive")
+                .help(EXCLUDE_HELP),
+        )
+        .arg(
+            clap::Arg::new("dedup_inodes")
+                .long("dedup-inodes")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["jobs", "concat"])
+                .help(DEDUP_INODES_HELP),
+        )
+        .arg(
+            clap::Arg::new("buffer_size")
+                .long("buffer-size")
+                .value_name("SIZE")
+                .value_parser(|s: &str| parse_buffer_size(s, SizeUnits::Binary))
+                .default_value("64KB")
+                .help("size of the buffer used to read files, up to 1GB"),
+        )
+        .arg(
+            clap::Arg::new("mmap")
+                .long("mmap")
+                .action(clap::ArgAction::SetTrue)
+                .help("read files through memory mapping instead of read(2)"),
+        )
+        .arg(
+            clap::Arg::new("strict")
+                .long("strict")
+                .action(clap::ArgAction::SetTrue)
+                .help("fail on a FILE whose size changes while being hashed instead of warning"),
+        )
+        .arg(
+            clap::Arg::new("retries")
+                .long("retries")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("0")
+                .help(RETRIES_HELP),
+        )
+        .arg(
+            clap::Arg::new("progress")
+                .long("progress")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("jobs")
+                .help("show the progress of hashing each FILE on the standard error"),
+        )
+        .arg(
+            clap::Arg::new("range")
+                .long("range")
+                .value_name("START-END")
+                .value_parser(|s: &str| parse_range(s, SizeUnits::Binary))
+                .conflicts_with_all([
+                    "algorithm",
+                    "mmap",
+                    "tee",
+                    "explain",
+                    "match_etag",
+                    "check",
+                    "combine",
+                ])
+                .help(RANGE_HELP),
+        )
+        .arg(
+            clap::Arg::new("stdin_size")
+                .long("stdin-size")
+                .value_name("SIZE")
+                .value_parser(|s: &str| s3etag::parse_size_with_units(s, SizeUnits::Binary))
+                .conflicts_with_all(["range", "match_etag", "check"])
+                .help(STDIN_SIZE_HELP),
+        )
+        .arg(
+            clap::Arg::new("tee")
+                .long("tee")
+                .value_name("DEST")
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .conflicts_with_all([
+                    "recursive",
+                    "mmap",
+                    "jobs",
+                    "explain",
+                    "match_etag",
+                    "check",
+                ])
+                .help("copy the single FILE to DEST while hashing it"),
+        )
+        .arg(
+            clap::Arg::new("output")
+                .long("output")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .conflicts_with_all(["explain", "match_etag", "check"])
+                .help(OUTPUT_HELP),
+        )
+        .arg(
+            clap::Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(output::Format::NAMES)
+                .default_value("text")
+                .help(FORMAT_HELP),
+        )
+        .arg(
+            clap::Arg::new("etag_only")
+                .long("etag-only")
+                .visible_alias("no-filename")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("format")
+                .help("print only ETags without filenames"),
+        )
+        .arg(
+            clap::Arg::new("zero")
+                .short('z')
+                .long("zero")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("format")
+                .help("end each output line with NUL, not newline"),
+        )
+        .arg(
+            clap::Arg::new("tag")
+                .long("tag")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["format", "etag_only"])
+                .help("print BSD-style `S3ETAG (FILE) = ETAG` lines"),
+        )
+        .arg(
+            clap::Arg::new("tab")
+                .long("tab")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["format", "etag_only", "tag"])
+                .help("separate ETags and filenames with a tab instead of padding spaces"),
+        )
+        .arg(
+            clap::Arg::new("size")
+                .short('l')
+                .long("size")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["format", "etag_only", "tag"])
+                .help("print the size of each FILE in bytes between the ETag and the filename"),
+        )
+        .arg(
+            clap::Arg::new("base64")
+                .long("base64")
+                .action(clap::ArgAction::SetTrue)
+                .help("print ETags in base64 as in the `x-amz-checksum-*` headers"),
+        )
+        .arg(
+            clap::Arg::new("both")
+                .long("both")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "algorithm",
+                    "format",
+                    "tag",
+                    "base64",
+                    "content_md5",
+                    "combine",
+                    "expect",
+                    "explain",
+                    "match_etag",
+                    "check",
+                ])
+                .help(BOTH_HELP),
+        )
+        .arg(
+            clap::Arg::new("content_md5")
+                .long("content-md5")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "algorithm",
+                    "force_multipart",
+                    "range",
+                    "tag",
+                    "expect",
+                    "explain",
+                    "match_etag",
+                    "check",
+                ])
+                .help(CONTENT_MD5_HELP),
+        )
+        .arg(
+            clap::Arg::new("header")
+                .long("header")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "format",
+                    "expect",
+                    "explain",
+                    "match_etag",
+                    "check",
+                    "combine",
+                ])
+                .help(HEADER_HELP),
+        )
+        .arg(
+            clap::Arg::new("combine")
+                .long("combine")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "algorithm",
+                    "format",
+                    "etag_only",
+                    "tag",
+                    "tab",
+                    "size",
+                    "base64",
+                ])
+                .help(COMBINE_HELP),
+        )
+        .arg(
+            clap::Arg::new("concat")
+                .long("concat")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["algorithm", "recursive", "mmap", "range", "combine", "jobs"])
+                .help(CONCAT_HELP),
+        )
+        .arg(
+            clap::Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .value_parser(clap::value_parser!(NonZeroUsize))
+                .default_value("1")
+                .help("number of files processed concurrently"),
+        )
+        .arg(
+            clap::Arg::new("stats")
+                .long("stats")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["explain", "match_etag", "check"])
+                .help(STATS_HELP),
+        )
+        .arg(
+            clap::Arg::new("expect")
+                .long("expect")
+                .value_name("ETAG")
+                .value_parser(|s: &str| s.parse::<ETag>())
+                .conflicts_with_all([
+                    "algorithm",
+                    "format",
+                    "etag_only",
+                    "zero",
+                    "tag",
+                    "tab",
+                    "size",
+                    "base64",
+                    "combine",
+                ])
+                .help("print nothing and exit with failure if any FILE does not match ETAG"),
+        )
+        .arg(
+            clap::Arg::new("explain")
+                .long("explain")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "algorithm",
+                    "recursive",
+                    "format",
+                    "etag_only",
+                    "zero",
+                    "tag",
+                    "tab",
+                    "size",
+                    "base64",
+                    "expect",
+                    "combine",
+                    "concat",
+                ])
+                .help(EXPLAIN_HELP),
+        )
+        .arg(
+            clap::Arg::new("match_etag")
+                .long("match-etag")
+                .value_name("ETAG")
+                .value_parser(|s: &str| s.parse::<ETag>())
+                .conflicts_with_all([
+                    "algorithm",
+                    "recursive",
+                    "format",
+                    "etag_only",
+                    "zero",
+                    "tag",
+                    "tab",
+                    "size",
+                    "base64",
+                    "expect",
+                    "combine",
+                    "concat",
+                    "explain",
+                ])
+                .help(MATCH_ETAG_HELP),
+        )
+        .arg(
+            clap::Arg::new("check")
+                .short('c')
+                .long("check")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "algorithm",
+                    "recursive",
+                    "format",
+                    "etag_only",
+                    "zero",
+                    "tag",
+                    "tab",
+                    "size",
+                    "base64",
+                    "expect",
+                    "combine",
+                    "concat",
+                    "explain",
+                ])
+                .help(CHECK_HELP),
+        )
+        .arg(
+            clap::Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help(COLOR_HELP),
+        );
+    #[cfg(feature = "flate2")]
+    let command = command.arg(
+        clap::Arg::new("gunzip")
+            .long("gunzip")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all([
+                "algorithm",
+                "mmap",
+                "range",
+                "stdin_size",
+                "concat",
+                "explain",
+                "match_etag",
+            ])
+            .help(GUNZIP_HELP),
+    );
+    let matches = command.get_matches();
+    interrupt::install();
 
     let mut exit_code = process::ExitCode::SUCCESS;
-    let mut writer = io::LineWriter::new(io::stdout().lock());
-    let mut buffer = vec![0u8; 64 * 1024].into_boxed_slice();
 
-    let config = Config {
-        threshold: *matches.get_one::<NonZeroU64>("threshold").unwrap(),
-        chunksize: *matches.get_one::<NonZeroUsize>("chunksize").unwrap(),
+    let units = match matches.get_one::<String>("units").unwrap().as_str() {
+        "decimal" => SizeUnits::Decimal,
+        _ => SizeUnits::Binary,
+    };
+    // The sizes validated in the binary units are parsed again in the specified units, which
+    // never makes them larger and thus never fails.
+    let size_arg = |id| {
+        matches
+            .get_raw(id)
+            .unwrap()
+            .next()
+            .unwrap()
+            .to_str()
+            .unwrap()
+    };
+    let mut threshold = parse_threshold(size_arg("threshold"), units).unwrap();
+    let mut chunksize = parse_chunksize(size_arg("chunksize"), units).unwrap();
+    let range = match matches.get_raw("range") {
+        Some(mut values) => match parse_range(values.next().unwrap().to_str().unwrap(), units) {
+            Ok(range) => Some(range),
+            Err(e) => {
+                eprintln!("error: invalid value for '--range': {}", e);
+                return process::ExitCode::FAILURE;
+            }
+        },
+        None => None,
     };
+    let stdin_size = matches
+        .get_raw("stdin_size")
+        .map(|mut values| values.next().unwrap().to_str().unwrap())
+        .map(|s| s3etag::parse_size_with_units(s, units).unwrap());
+    // The buffer is not aligned to the chunksize because reads that cross chunk boundaries cost
+    // nothing measurable compared to MD5 itself.
+    let buffer_size = parse_buffer_size(size_arg("buffer_size"), units).unwrap();
+    let mut buffer = vec![0u8; buffer_size].into_boxed_slice();
+    if let Some((name, path)) = matches
+        .get_one::<String>("profile")
+        .zip(profile::config_path())
+    {
+        let is_default =
+            |id| matches.value_source(id) == Some(clap::parser::ValueSource::DefaultValue);
+        // awscli always reads the sizes in the config file in binary units regardless of --units.
+        let result = profile::load(&path, name).and_then(|settings| {
+            let invalid = |key, e| io::Error::other(format!("{}: {}", key, e));
+            if let Some(value) = settings
+                .multipart_threshold
+                .filter(|_| is_default("threshold"))
+            {
+                threshold = parse_threshold(&value, SizeUnits::Binary)
+                    .map_err(|e| invalid("multipart_threshold", e))?;
+            }
+            if let Some(value) = settings
+                .multipart_chunksize
+                .filter(|_| is_default("chunksize"))
+            {
+                chunksize = parse_chunksize(&value, SizeUnits::Binary)
+                    .map_err(|e| invalid("multipart_chunksize", e))?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            eprintln!("error: {}: {}", path.display(), e);
+            return process::ExitCode::FAILURE;
+        }
+    }
+
+    let tee = match matches.get_one::<path::PathBuf>("tee") {
+        Some(_) if matches.get_many::<path::PathBuf>("files").unwrap().len() > 1 => {
+            eprintln!("error: --tee accepts only one FILE");
+            return process::ExitCode::FAILURE;
+        }
+        // Refuse to truncate the source before it is read.
+        Some(dest) if is_same_file(matches.get_one::<path::PathBuf>("files").unwrap(), dest) => {
+            eprintln!(
+                "error: {}: destination is the FILE being hashed",
+                dest.display()
+            );
+            return process::ExitCode::FAILURE;
+        }
+        Some(dest) => match tee::Dest::create(dest) {
+            Ok(dest) => Some(dest),
+            Err(e) => {
+                eprintln!("error: {}: {}", dest.display(), e);
+                return process::ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    if stdin_size.is_some()
+        && !matches
+            .get_many::<path::PathBuf>("files")
+            .unwrap()
+            .any(|f| f.as_os_str() == "-")
+    {
+        eprintln!("error: --stdin-size requires `-` among FILEs");
+        return process::ExitCode::FAILURE;
+    }
 
-    let mut files = matches
-        .get_many::<path::PathBuf>("files")
-        .unwrap()
-        .fuse()
-        .map(|filename| (open_and_fadvise_seq(filename), filename));
+    let mut config = Config {
+        threshold,
+        mode: if matches.get_flag("force_single") {
+            Some(Mode::Single)
+        } else if matches.get_flag("force_multipart") {
+            Some(Mode::Multipart)
+        } else {
+            None
+        },
+        chunksize,
+        auto_chunksize: matches.get_flag("auto_chunksize"),
+        mmap: matches.get_flag("mmap"),
+        strict: matches.get_flag("strict"),
+        retries: *matches.get_one::<u32>("retries").unwrap(),
+        algorithm: *matches.get_one::<Algorithm>("algorithm").unwrap(),
+        progress: matches.get_flag("progress"),
+        range,
+        stdin_size,
+        tee,
+        #[cfg(feature = "flate2")]
+        gunzip: matches.get_flag("gunzip"),
+    };
 
-    let mut next = files.next();
-    while let Some((result_file, filename)) = next {
-        // announce the next file before processing the current one
-        next = files.next();
+    if let Some(target) = matches.get_one::<ETag>("match_etag") {
+        let mut writer = io::LineWriter::new(io::stdout().lock());
+        for filename in matches.get_many::<path::PathBuf>("files").unwrap() {
+            if interrupt::is_set() {
+                break;
+            }
+            if let Err(e) = detect::match_etag(filename, target, &mut writer, &mut buffer) {
+                exit_code = process::ExitCode::FAILURE;
+                eprintln!("error: {}: {}", filename.display(), e);
+            }
+        }
+        return interrupt::exit_code(exit_code);
+    }
+
+    if matches.get_flag("explain") {
+        let mut writer = io::LineWriter::new(io::stdout().lock());
+        for filename in matches.get_many::<path::PathBuf>("files").unwrap() {
+            if interrupt::is_set() {
+                break;
+            }
+            if let Err(e) = explain_file(filename, &config, &mut writer) {
+                exit_code = process::ExitCode::FAILURE;
+                eprintln!("error: {}: {}", filename.display(), e);
+            }
+        }
+        return interrupt::exit_code(exit_code);
+    }
 
-        if let Err(e) = process_file(result_file, filename, &config, &mut writer, &mut buffer) {
+    if matches.get_flag("check") {
+        let color = match matches.get_one::<String>("color").unwrap().as_str() {
+            "always" => true,
+            "never" => false,
+            _ => {
+                io::IsTerminal::is_terminal(&io::stdout())
+                    && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        };
+        let mut writer = io::LineWriter::new(io::stdout().lock());
+        for manifest in matches.get_many::<path::PathBuf>("files").unwrap() {
+            if interrupt::is_set() {
+                break;
+            }
+            match check::check_manifest(manifest, &mut config, color, &mut writer, &mut buffer) {
+                Ok(true) => (),
+                Ok(false) => exit_code = process::ExitCode::FAILURE,
+                Err(e) => {
+                    exit_code = process::ExitCode::FAILURE;
+                    eprintln!("error: {}: {}", manifest.display(), e);
+                }
+            }
+        }
+        return interrupt::exit_code(exit_code);
+    }
+
+    let options = output::Options {
+        format: output::Format::from_name(matches.get_one::<String>("format").unwrap()).unwrap(),
+        etag_only: matches.get_flag("etag_only"),
+        zero_terminated: matches.get_flag("zero"),
+        base64: matches.get_flag("base64") || matches.get_flag("content_md5"),
+        content_md5: matches.get_flag("content_md5"),
+        both: matches.get_flag("both"),
+        tag: matches.get_flag("tag").then(|| config.algorithm.tag()),
+        tab: matches.get_flag("tab"),
+        size: matches.get_flag("size"),
+        expect: matches.get_one::<ETag>("expect").copied(),
+        combine: matches.get_flag("combine"),
+    };
+    let output = match matches.get_one::<path::PathBuf>("output") {
+        // Refuse to truncate a FILE given explicitly before it is read.
+        Some(path)
+            if matches
+                .get_many::<path::PathBuf>("files")
+                .unwrap()
+                .any(|f| is_same_file(f, path)) =>
+        {
+            eprintln!(
+                "error: {}: output file is also an input FILE",
+                path.display()
+            );
+            return process::ExitCode::FAILURE;
+        }
+        Some(path) => match fs::File::create(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+                return process::ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    // The output file found in directories is excluded from the inputs because it is being
+    // written.
+    let output_key = output.as_ref().and_then(file_key);
+    let inner: Box<dyn io::Write> = match output {
+        Some(file) => Box::new(file),
+        None => Box::new(io::stdout().lock()),
+    };
+    // `LineWriter` is useless for NUL-terminated records.
+    let writer: Box<dyn io::Write> = if options.zero_terminated {
+        Box::new(io::BufWriter::new(inner))
+    } else {
+        Box::new(io::LineWriter::new(inner))
+    };
+    let mut printer = output::Printer::new(writer, options);
+    if matches.get_flag("header") {
+        if let Err(e) = printer.print_line(&check::Header::new(&config)) {
+            eprintln!("error: {}", e);
+            return process::ExitCode::FAILURE;
+        }
+    }
+
+    let started = time::Instant::now();
+    let mut stats = Stats::default();
+    let mut files = walk::FileArgs::new(
+        matches.get_many::<path::PathBuf>("files").unwrap(),
+        matches.get_flag("recursive"),
+        matches.get_flag("follow_symlinks"),
+        matches
+            .get_many::<glob::Pattern>("exclude")
+            .map_or_else(Vec::new, |e| e.cloned().collect()),
+    )
+    .fuse()
+    .map(|entry| match entry {
+        Ok(filename) => (open_input(&filename), filename),
+        Err((filename, e)) => (Err(e), filename),
+    })
+    .filter(|(result_input, filename)| {
+        let is_output = output_key.is_some()
+            && matches!(result_input, Ok(input) if inode_key(input) == output_key);
+        if is_output {
+            eprintln!("warning: {}: skipping the output file", filename.display());
+        }
+        !is_output
+    });
+
+    let jobs = *matches.get_one::<NonZeroUsize>("jobs").unwrap();
+    if matches.get_flag("concat") {
+        let filenames = matches.get_many::<path::PathBuf>("files").unwrap();
+        let filename = path::Path::new("-");
+        let result =
+            hash_concat(filenames, &config, &mut buffer, output_key).and_then(|(etag, size)| {
+                printer
+                    .print(filename, &Digest::ETag(etag), size)
+                    .map(|_| size)
+                    .map_err(|e| (filename, e))
+            });
+        match result {
+            Ok(size) => stats.record(&Ok(size)),
+            Err((filename, e)) => {
+                eprintln!("error: {}: {}", filename.display(), e);
+                stats.record(&Err(e));
+            }
+        }
+    } else if jobs.get() > 1 {
+        process_files_parallel(files, jobs, &config, &mut printer, &mut stats, buffer.len());
+    } else {
+        let mut inodes = matches
+            .get_flag("dedup_inodes")
+            .then(collections::HashMap::new);
+        #[cfg(not(unix))]
+        if inodes.take().is_some() {
+            eprintln!("warning: --dedup-inodes is ignored on this platform");
+        }
+
+        let mut next = files.next();
+        while let Some((result_input, filename)) = next {
+            if interrupt::is_set() {
+                break;
+            }
+            // announce the next file before processing the current one
+            next = files.next();
+
+            let result = process_file(
+                result_input,
+                &filename,
+                &config,
+                &mut printer,
+                &mut buffer,
+                inodes.as_mut(),
+            );
+            stats.record(&result);
+            if let Err(e) = result {
+                eprintln!("error: {}: {}", filename.display(), e);
+            }
+        }
+    }
+    if stats.failed > 0 {
+        exit_code = process::ExitCode::FAILURE;
+        if matches.get_flag("combine") {
+            printer.discard_combined();
+            eprintln!("error: combined ETag is not printed because some FILEs failed");
+        }
+    }
+
+    if let Err(e) = printer.finish() {
+        exit_code = process::ExitCode::FAILURE;
+        eprintln!("error: {}", e);
+    }
+
+    if let Some(dest) = &config.tee {
+        if let Err(e) = dest.finish() {
             exit_code = process::ExitCode::FAILURE;
-            eprintln!("error: {}: {}", filename.display(), e);
+            eprintln!("error: {}: {}", dest.path().display(), e);
         }
     }
 
-    exit_code
+    if matches.get_flag("stats") {
+        eprintln!(
+            "{} files processed, {} failed, {} bytes hashed in {:.3} s",
+            stats.files,
+            stats.failed,
+            stats.bytes,
+            started.elapsed().as_secs_f64()
+        );
+    }
+
+    interrupt::exit_code(exit_code)
 }
 
 /// Parses the threshold argument.
-fn parse_threshold(s: &str) -> Result<NonZeroU64, Box<dyn error::Error + Sync + Send>> {
-    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
-        Some(0) => return Err("cannot parse integer".into()),
-        None => (s, Ok(1u64)),
-        Some(pos) => (
-            &s[..pos],
-            match &s[pos..] {
-                "KB" => Ok(1 << 10),
-                "MB" => Ok(1 << 20),
-                "GB" => Ok(1 << 30),
-                "TB" => Ok(1 << 40),
-                _ => Err("unknown size suffix"),
-            },
-        ),
-    };
-    num.parse::<NonZeroU64>()?
-        .checked_mul(unit?.try_into()?)
-        .ok_or_else(|| "too large threshold".into())
+fn parse_threshold(
+    s: &str,
+    units: SizeUnits,
+) -> Result<NonZeroU64, Box<dyn error::Error + Sync + Send>> {
+    NonZeroU64::new(s3etag::parse_size_with_units(s, units)?).ok_or_else(|| {
+        "zero threshold, which awscli rejects; use --force-multipart to compute multipart ETags \
+        for all FILEs"
+            .into()
+    })
 }
 
 /// Parses the chunksize argument.
-fn parse_chunksize(s: &str) -> Result<NonZeroUsize, Box<dyn error::Error + Sync + Send>> {
-    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
-        Some(0) => return Err("cannot parse integer".into()),
-        None => (s, Ok(1usize)),
-        Some(pos) => (
-            &s[..pos],
-            match &s[pos..] {
-                "KB" => Ok(1 << 10),
-                "MB" => Ok(1 << 20),
-                "GB" => Ok(1 << 30),
-                "TB" => Ok(1 << 40),
-                _ => Err("unknown size suffix"),
-            },
-        ),
-    };
-    num.parse::<NonZeroUsize>()?
-        .checked_mul(unit?.try_into()?)
-        .ok_or_else(|| "too large chunksize".into())
+fn parse_chunksize(
+    s: &str,
+    units: SizeUnits,
+) -> Result<NonZeroUsize, Box<dyn error::Error + Sync + Send>> {
+    let n = usize::try_from(s3etag::parse_size_with_units(s, units)?)
+        .map_err(|_| "too large chunksize")?;
+    NonZeroUsize::new(n).ok_or_else(|| "zero chunksize".into())
+}
+
+/// Parses the range argument into the start and end offsets.
+fn parse_range(
+    s: &str,
+    units: SizeUnits,
+) -> Result<(u64, u64), Box<dyn error::Error + Sync + Send>> {
+    let (start, end) = s.split_once('-').ok_or("expected START-END")?;
+    let start = s3etag::parse_size_with_units(start, units)?;
+    let end = s3etag::parse_size_with_units(end, units)?;
+    if start >= end {
+        return Err("empty range".into());
+    }
+    Ok((start, end))
+}
+
+/// Parses the buffer size argument.
+fn parse_buffer_size(
+    s: &str,
+    units: SizeUnits,
+) -> Result<usize, Box<dyn error::Error + Sync + Send>> {
+    const MAX_SIZE: u64 = 1 << 30;
+    match s3etag::parse_size_with_units(s, units)? {
+        0 => Err("zero buffer size".into()),
+        n if n > MAX_SIZE => Err("too large buffer size".into()),
+        n => Ok(n as usize),
+    }
 }
 
 /// Opens a file and calls `posix_fadvise` with `POSIX_FADV_SEQUENTIAL`.
+///
+/// This function returns an error for a directory, which may be opened but cannot be read.
 fn open_and_fadvise_seq(filename: &path::Path) -> io::Result<fs::File> {
     let file = fs::File::open(filename)?;
+    if file.metadata()?.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::IsADirectory,
+            "is a directory",
+        ));
+    }
 
     // Conditions are copied from the `nix::fcntl::posix_fadvise()` source code, except that the
     // `linux_android` alias is expanded to the two `target_os` conditions.
@@ -148,55 +959,862 @@ fn open_and_fadvise_seq(filename: &path::Path) -> io::Result<fs::File> {
 #[derive(Debug)]
 struct Config {
     threshold: NonZeroU64,
+    /// The mode forced regardless of the threshold, if any.
+    mode: Option<Mode>,
     chunksize: NonZeroUsize,
+    auto_chunksize: bool,
+    mmap: bool,
+    strict: bool,
+    /// The number of consecutive retries of a read that fails with a transient error.
+    retries: u32,
+    algorithm: Algorithm,
+    progress: bool,
+    /// The byte range whose plain MD5 digest is computed instead of the ETag.
+    range: Option<(u64, u64)>,
+    /// The length of the standard input given in advance, if any.
+    stdin_size: Option<u64>,
+    tee: Option<tee::Dest>,
+    #[cfg(feature = "flate2")]
+    gunzip: bool,
 }
 
-/// Computes and prints the ETag for a file.
+/// The single-part or multipart mode of ETag calculation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Single,
+    Multipart,
+}
+
+impl Config {
+    /// Returns the threshold in effect for an input of known size, which is overridden by the
+    /// forced mode.
+    ///
+    /// No input reaches the maximum threshold, whereas any non-empty input reaches the minimum.
+    fn threshold(&self) -> NonZeroU64 {
+        match self.mode {
+            Some(Mode::Single) => NonZeroU64::MAX,
+            Some(Mode::Multipart) => NonZeroU64::MIN,
+            None => self.threshold,
+        }
+    }
+
+    /// Creates an ETag hasher for a stream of unknown length, which buffers data up to the
+    /// threshold unless the mode is forced.
+    fn streaming_etagger(&self, chunksize: NonZeroUsize) -> S3Etagger<Md5> {
+        match self.mode {
+            Some(Mode::Single) => S3Etagger::single(),
+            Some(Mode::Multipart) => S3Etagger::multipart(chunksize),
+            None => S3Etagger::new(self.threshold, chunksize),
+        }
+    }
+
+    /// Returns the chunksize to use for an input of `size` bytes.
+    fn chunksize_for(&self, size: Option<u64>) -> NonZeroUsize {
+        if self.auto_chunksize {
+            s3etag::adjust_chunksize(self.chunksize, size)
+        } else {
+            self.chunksize
+        }
+    }
+
+    /// Checks if the number of bytes hashed equals the file size used to select the mode.
+    ///
+    /// A file that grows or shrinks while being hashed may produce a wrong ETag, which is reported
+    /// as an error in the strict mode or as a warning otherwise.
+    fn check_size(&self, filename: &path::Path, expected: u64, actual: u64) -> io::Result<()> {
+        if expected != actual {
+            let msg = format!(
+                "file size changed from {} to {} bytes while being hashed",
+                expected, actual
+            );
+            if self.strict {
+                return Err(io::Error::other(msg));
+            }
+            eprintln!(
+                "warning: {}: {}; the result may be wrong",
+                filename.display(),
+                msg
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks if the number of bytes read from the standard input equals the length given in
+    /// advance, reporting a mismatch as [`Config::check_size`] does.
+    fn check_stdin_size(&self, filename: &path::Path, actual: u64) -> io::Result<()> {
+        match self.stdin_size {
+            Some(expected) if expected != actual => {
+                let msg = format!(
+                    "stream length of {} bytes differs from --stdin-size of {} bytes",
+                    actual, expected
+                );
+                if self.strict {
+                    return Err(io::Error::other(msg));
+                }
+                eprintln!(
+                    "warning: {}: {}; the result may be wrong",
+                    filename.display(),
+                    msg
+                );
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// The checksum algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    /// The ETag.
+    Md5,
+    #[cfg(feature = "crc32fast")]
+    Crc32,
+    #[cfg(feature = "crc32c")]
+    Crc32c,
+    #[cfg(feature = "crc64fast-nvme")]
+    Crc64Nvme,
+    #[cfg(feature = "sha1")]
+    Sha1,
+    #[cfg(feature = "sha2")]
+    Sha256,
+}
+
+impl Algorithm {
+    const NAMES: &'static [&'static str] = &[
+        "md5",
+        #[cfg(feature = "crc32fast")]
+        "crc32",
+        #[cfg(feature = "crc32c")]
+        "crc32c",
+        #[cfg(feature = "crc64fast-nvme")]
+        "crc64nvme",
+        #[cfg(feature = "sha1")]
+        "sha1",
+        #[cfg(feature = "sha2")]
+        "sha256",
+    ];
+
+    /// Returns the tag for the BSD-style text format.
+    fn tag(self) -> &'static str {
+        match self {
+            Self::Md5 => output::ETAG_TAG,
+            #[cfg(feature = "crc32fast")]
+            Self::Crc32 => "CRC32",
+            #[cfg(feature = "crc32c")]
+            Self::Crc32c => "CRC32C",
+            #[cfg(feature = "crc64fast-nvme")]
+            Self::Crc64Nvme => "CRC64NVME",
+            #[cfg(feature = "sha1")]
+            Self::Sha1 => "SHA1",
+            #[cfg(feature = "sha2")]
+            Self::Sha256 => "SHA256",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            #[cfg(feature = "crc32fast")]
+            Self::Crc32 => "crc32",
+            #[cfg(feature = "crc32c")]
+            Self::Crc32c => "crc32c",
+            #[cfg(feature = "crc64fast-nvme")]
+            Self::Crc64Nvme => "crc64nvme",
+            #[cfg(feature = "sha1")]
+            Self::Sha1 => "sha1",
+            #[cfg(feature = "sha2")]
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "md5" => Some(Self::Md5),
+            #[cfg(feature = "crc32fast")]
+            "crc32" => Some(Self::Crc32),
+            #[cfg(feature = "crc32c")]
+            "crc32c" => Some(Self::Crc32c),
+            #[cfg(feature = "crc64fast-nvme")]
+            "crc64nvme" => Some(Self::Crc64Nvme),
+            #[cfg(feature = "sha1")]
+            "sha1" => Some(Self::Sha1),
+            #[cfg(feature = "sha2")]
+            "sha256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Parses an algorithm name in any case, reporting the algorithms of S3 that are disabled in
+    /// this build with the feature that enables each.
+    fn parse(name: &str) -> Result<Self, String> {
+        let name = name.to_ascii_lowercase();
+        Self::from_name(&name).ok_or_else(|| {
+            let disabled = match name.as_str() {
+                "crc32" => Some(("CRC32", "crc32fast")),
+                "crc32c" => Some(("CRC32C", "crc32c")),
+                "crc64nvme" => Some(("CRC64NVME", "crc64fast-nvme")),
+                "sha1" => Some(("SHA-1", "sha1")),
+                "sha256" => Some(("SHA-256", "sha2")),
+                _ => None,
+            };
+            match disabled {
+                Some((algorithm, feature)) => format!(
+                    "{} support not enabled in this build (enable feature `{}`)",
+                    algorithm, feature
+                ),
+                None => format!(
+                    "unknown algorithm `{}` (expected one of: {})",
+                    name,
+                    Self::NAMES.join(", ")
+                ),
+            }
+        })
+    }
+}
+
+/// The parser of `--algorithm` that lists the algorithms enabled in this build as the possible
+/// values while reporting the disabled ones specifically.
+#[derive(Clone, Copy, Debug)]
+struct AlgorithmParser;
+
+impl clap::builder::TypedValueParser for AlgorithmParser {
+    type Value = Algorithm;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        clap::builder::TypedValueParser::parse_ref(&Algorithm::parse, cmd, arg, value)
+    }
+
+    fn possible_values(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        Some(Box::new(
+            Algorithm::NAMES
+                .iter()
+                .map(clap::builder::PossibleValue::new),
+        ))
+    }
+}
+
+/// A computed ETag or additional checksum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Digest {
+    ETag(ETag),
+    Checksum(Checksum),
+}
+
+impl Digest {
+    fn part_count(&self) -> Option<NonZeroUsize> {
+        match self {
+            Self::ETag(etag) => etag.part_count(),
+            Self::Checksum(checksum) => checksum.part_count(),
+        }
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ETag(etag) => fmt::Display::fmt(etag, f),
+            Self::Checksum(checksum) => fmt::Display::fmt(checksum, f),
+        }
+    }
+}
+
+/// An input source specified by a FILE argument.
+#[derive(Debug)]
+enum Input {
+    File(fs::File),
+    Stdin,
+}
+
+/// Opens a FILE argument, treating `-` as the standard input.
+fn open_input(filename: &path::Path) -> io::Result<Input> {
+    if filename.as_os_str() == "-" {
+        Ok(Input::Stdin)
+    } else {
+        open_and_fadvise_seq(filename).map(Input::File)
+    }
+}
+
+/// The results of files hashed, keyed by the device and inode numbers, to be reused for the hard
+/// links to them.
+type InodeCache = collections::HashMap<(u64, u64), (Digest, u64)>;
+
+/// Computes and prints the ETag for a file, reusing the result for the same file in `inodes` if
+/// given.
 fn process_file(
-    result_file: io::Result<fs::File>,
+    result_input: io::Result<Input>,
     filename: &path::Path,
     config: &Config,
-    writer: &mut impl io::Write,
+    printer: &mut output::Printer<impl io::Write>,
     buffer: &mut [u8],
-) -> io::Result<()> {
-    let etag = {
-        fn compute_etag(
-            mut hasher: impl ETagHasher,
-            file: &mut fs::File,
-            buffer: &mut [u8],
-        ) -> io::Result<ETag> {
-            loop {
-                match io::Read::read(file, buffer) {
-                    Ok(0) => break Ok(hasher.finalize()),
-                    Ok(n) => hasher.update(&buffer[..n]),
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
-                    Err(e) => break Err(e),
+    inodes: Option<&mut InodeCache>,
+) -> io::Result<u64> {
+    let input = result_input?;
+    let (digest, size) = match inodes.zip(inode_key(&input)) {
+        Some((inodes, key)) => match inodes.entry(key) {
+            collections::hash_map::Entry::Occupied(e) => e.get().clone(),
+            collections::hash_map::Entry::Vacant(e) => e
+                .insert(hash_input(input, filename, config, buffer)?)
+                .clone(),
+        },
+        None => hash_input(input, filename, config, buffer)?,
+    };
+    printer.print(filename, &digest, size)?;
+    Ok(size)
+}
+
+/// Returns the device and inode numbers that identify an input file, or `None` if unavailable.
+fn inode_key(input: &Input) -> Option<(u64, u64)> {
+    match input {
+        Input::File(file) => file_key(file),
+        Input::Stdin => None,
+    }
+}
+
+/// Returns the device and inode numbers of an open file, or `None` if unavailable.
+fn file_key(file: &fs::File) -> Option<(u64, u64)> {
+    file.metadata().ok().as_ref().and_then(metadata_key)
+}
+
+/// Returns true if two paths refer to the same existing file, which is never detected on
+/// platforms without inode numbers.
+fn is_same_file(a: &path::Path, b: &path::Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => metadata_key(&a).is_some_and(|key| metadata_key(&b) == Some(key)),
+        _ => false,
+    }
+}
+
+/// Returns the device and inode numbers in the metadata of a file, or `None` if unavailable.
+#[cfg(unix)]
+fn metadata_key(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt as _;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn metadata_key(_: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Counters of the files processed, reported by `--stats`.
+#[derive(Debug, Default)]
+struct Stats {
+    files: u64,
+    failed: u64,
+    bytes: u64,
+}
+
+impl Stats {
+    /// Counts a file by the result of [`process_file`].
+    fn record(&mut self, result: &io::Result<u64>) {
+        self.files += 1;
+        match result {
+            Ok(size) => self.bytes += size,
+            Err(_) => self.failed += 1,
+        }
+    }
+}
+
+/// Computes and prints the ETags for files using `jobs` worker threads, counting them in `stats`.
+///
+/// Each worker thread opens and hashes files with its own buffer of `buffer_size` bytes, while the
+/// results are printed by the calling thread in the input order.
+fn process_files_parallel(
+    files: impl Iterator<Item = (io::Result<Input>, path::PathBuf)> + Send,
+    jobs: NonZeroUsize,
+    config: &Config,
+    printer: &mut output::Printer<impl io::Write>,
+    stats: &mut Stats,
+    buffer_size: usize,
+) {
+    let files = sync::Mutex::new(files.enumerate());
+    let (tx, rx) = sync::mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..jobs.get() {
+            let (files, tx) = (&files, tx.clone());
+            s.spawn(move || {
+                let mut buffer = vec![0u8; buffer_size].into_boxed_slice();
+                while !interrupt::is_set() {
+                    let Some((i, (result_input, filename))) = files.lock().unwrap().next() else {
+                        break;
+                    };
+                    let result = result_input
+                        .and_then(|input| hash_input(input, &filename, config, &mut buffer));
+                    if tx.send((i, filename, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // reorder the results that arrive in the completion order
+        let mut pending = collections::BTreeMap::new();
+        let mut next_index = 0;
+        for (i, filename, result) in rx {
+            pending.insert(i, (filename, result));
+            while let Some((filename, result)) = pending.remove(&next_index) {
+                next_index += 1;
+                let result = result.and_then(|(digest, size)| {
+                    printer.print(&filename, &digest, size)?;
+                    Ok(size)
+                });
+                stats.record(&result);
+                if let Err(e) = result {
+                    eprintln!("error: {}: {}", filename.display(), e);
                 }
             }
         }
+    })
+}
 
-        let mut file = result_file?;
-        if file.metadata()?.len() < config.threshold.into() {
-            let hasher = Md5::default();
-            compute_etag(hasher, &mut file, buffer)
-        } else {
-            let hasher = ETagHasherMulti::<Md5>::new(config.chunksize);
-            compute_etag(hasher, &mut file, buffer)
+/// Computes the ETag or checksum for an input and returns it with the number of bytes hashed.
+fn hash_input(
+    input: Input,
+    filename: &path::Path,
+    config: &Config,
+    buffer: &mut [u8],
+) -> io::Result<(Digest, u64)> {
+    if let Some(range) = config.range {
+        return hash_range(input, range);
+    }
+
+    let (digest, size) = match (config.algorithm, input) {
+        #[cfg(feature = "flate2")]
+        (Algorithm::Md5, input) if config.gunzip => hash_gunzip(input, filename, config, buffer),
+        (Algorithm::Md5, Input::File(file)) => {
+            hash_file(file, filename, config, buffer).map(|(e, n)| (Digest::ETag(e), n))
+        }
+        (Algorithm::Md5, Input::Stdin) => {
+            // The stream length is unknown until EOF unless given in advance, so let the hasher
+            // buffer data up to the threshold to determine the mode.
+            let hasher = match config.stdin_size {
+                Some(len) => {
+                    let chunksize = config.chunksize_for(Some(len));
+                    S3Etagger::<Md5>::for_known_size(len, config.threshold(), chunksize)
+                }
+                None => config.streaming_etagger(config.chunksize_for(None)),
+            };
+            let mut reader = progress::Reader::new(
+                tee::Reader::new(io::stdin().lock(), config.tee.as_ref()),
+                filename,
+                config.stdin_size,
+                config.progress,
+            );
+            let (etag, n_bytes) = compute_etag(hasher, &mut reader, buffer)?;
+            config.check_stdin_size(filename, n_bytes)?;
+            Ok((Digest::ETag(etag), n_bytes))
+        }
+        #[cfg(feature = "crc32fast")]
+        (Algorithm::Crc32, input) => {
+            hash_checksum::<s3etag::Crc32>(input, filename, config, buffer, false)
+        }
+        #[cfg(feature = "crc32c")]
+        (Algorithm::Crc32c, input) => {
+            hash_checksum::<s3etag::Crc32c>(input, filename, config, buffer, false)
+        }
+        // CRC64NVME is always a full-object checksum even for multipart uploads.
+        #[cfg(feature = "crc64fast-nvme")]
+        (Algorithm::Crc64Nvme, input) => {
+            hash_checksum::<s3etag::Crc64Nvme>(input, filename, config, buffer, true)
+        }
+        #[cfg(feature = "sha1")]
+        (Algorithm::Sha1, input) => {
+            hash_checksum::<sha1::Sha1>(input, filename, config, buffer, false)
+        }
+        #[cfg(feature = "sha2")]
+        (Algorithm::Sha256, input) => {
+            hash_checksum::<sha2::Sha256>(input, filename, config, buffer, false)
         }
     }?;
 
-    write!(writer, "{:<39} ", etag)?;
+    if digest
+        .part_count()
+        .is_some_and(|n| n.get() > s3etag::MAX_PART_COUNT)
+    {
+        eprintln!(
+            "warning: {}: ETag has more than {} parts, which S3 does not allow; awscli would use \
+            a larger chunksize",
+            filename.display(),
+            s3etag::MAX_PART_COUNT
+        );
+    }
+    Ok((digest, size))
+}
 
-    #[cfg(unix)]
-    writer.write_all(filename.as_os_str().as_bytes())?;
-    #[cfg(not(unix))]
-    write!(writer, "{}", filename.display())?;
+/// Computes the ETag of the decompressed content of a gzip input and returns it with the number of
+/// decompressed bytes hashed.
+///
+/// The decompressed size is unknown until EOF, so the hasher buffers data up to the threshold to
+/// determine the mode, while the progress is shown in the compressed bytes read.
+#[cfg(feature = "flate2")]
+fn hash_gunzip(
+    input: Input,
+    filename: &path::Path,
+    config: &Config,
+    buffer: &mut [u8],
+) -> io::Result<(Digest, u64)> {
+    let (inner, size): (Box<dyn io::Read>, _) = match input {
+        Input::File(file) => {
+            let size = file.metadata()?.len();
+            let file = retry::Reader::new(file, filename, config.retries);
+            (Box::new(file), Some(size))
+        }
+        Input::Stdin => (Box::new(io::stdin().lock()), None),
+    };
+    let mut reader = tee::Reader::new(
+        gunzip::Reader::new(progress::Reader::new(
+            inner,
+            filename,
+            size,
+            config.progress,
+        )),
+        config.tee.as_ref(),
+    );
+    let hasher = config.streaming_etagger(config.chunksize_for(None));
+    compute_etag(hasher, &mut reader, buffer).map(|(e, n)| (Digest::ETag(e), n))
+}
 
-    writer.write_all(b"\n")
+/// Computes the additional checksum for an input and returns it with the number of bytes hashed.
+///
+/// The checksum is computed over the whole input regardless of the threshold if `full_object` is
+/// set, or as a composite checksum otherwise.
+#[cfg_attr(
+    not(any(
+        feature = "crc32fast",
+        feature = "crc32c",
+        feature = "crc64fast-nvme",
+        feature = "sha1",
+        feature = "sha2"
+    )),
+    allow(dead_code)
+)]
+fn hash_checksum<H: ChecksumHasher>(
+    input: Input,
+    filename: &path::Path,
+    config: &Config,
+    buffer: &mut [u8],
+    full_object: bool,
+) -> io::Result<(Digest, u64)> {
+    let size = match &input {
+        Input::File(file) => Some(file.metadata()?.len()),
+        Input::Stdin => config.stdin_size,
+    };
+    let mut hasher = match size {
+        _ if full_object => CompositeHasher::<H>::full_object(),
+        Some(len) => {
+            let chunksize = config.chunksize_for(Some(len));
+            CompositeHasher::for_known_size(len, config.threshold(), chunksize)
+        }
+        None => match config.mode {
+            Some(Mode::Single) => CompositeHasher::full_object(),
+            Some(Mode::Multipart) => CompositeHasher::composite(config.chunksize_for(None)),
+            None => CompositeHasher::new(config.threshold, config.chunksize_for(None)),
+        },
+    };
+    let len = match input {
+        Input::File(file) => match config.mmap.then(|| map_file(&file, filename)).flatten() {
+            Some(map) => {
+                hasher.update(&map);
+                map.len() as u64
+            }
+            None => {
+                let mut reader = progress::Reader::new(
+                    tee::Reader::new(
+                        retry::Reader::new(file, filename, config.retries),
+                        config.tee.as_ref(),
+                    ),
+                    filename,
+                    size,
+                    config.progress,
+                );
+                read_all(&mut reader, buffer, |data| hasher.update(data))?
+            }
+        },
+        Input::Stdin => {
+            let mut reader = progress::Reader::new(
+                tee::Reader::new(io::stdin().lock(), config.tee.as_ref()),
+                filename,
+                size,
+                config.progress,
+            );
+            let len = read_all(&mut reader, buffer, |data| hasher.update(data))?;
+            config.check_stdin_size(filename, len)?;
+            return Ok((Digest::Checksum(hasher.finalize()), len));
+        }
+    };
+    if let Some(size) = size {
+        config.check_size(filename, size, len)?;
+    }
+    Ok((Digest::Checksum(hasher.finalize()), len))
+}
+
+/// Computes the plain MD5 digest of a byte range of an input and returns it with the length of the
+/// range.
+fn hash_range(input: Input, (start, end): (u64, u64)) -> io::Result<(Digest, u64)> {
+    let Input::File(file) = input else {
+        return Err(io::Error::other(
+            "range is not applicable to the standard input",
+        ));
+    };
+    let size = file.metadata()?.len();
+    if end > size {
+        return Err(io::Error::other(format!(
+            "range exceeds the file size of {} bytes",
+            size
+        )));
+    }
+    let etag = s3etag::etag_of_range::<Md5>(file, start, end - start)?;
+    Ok((Digest::ETag(etag), end - start))
+}
+
+/// Computes the ETag of the concatenation of inputs as one object and returns it with the total
+/// number of bytes hashed.
+///
+/// The total size of the inputs selects the mode, and parts may span the boundaries between inputs.
+/// The standard input makes the total size unknown unless its length is given in advance, so the
+/// hasher buffers data up to the threshold as it does for a single stream. Hashing stops at the
+/// first input that cannot be opened or read, or that is the output file identified by
+/// `output_key`, whose name is returned with the error.
+fn hash_concat<'a>(
+    filenames: impl Iterator<Item = &'a path::PathBuf> + Clone,
+    config: &Config,
+    buffer: &mut [u8],
+    output_key: Option<(u64, u64)>,
+) -> Result<(ETag, u64), (&'a path::Path, io::Error)> {
+    let mut total = Some(0u64);
+    for filename in filenames.clone() {
+        if filename.as_os_str() == "-" {
+            total = total.zip(config.stdin_size).map(|(total, len)| total + len);
+        } else {
+            let len = fs::metadata(filename)
+                .map_err(|e| (filename.as_path(), e))?
+                .len();
+            total = total.map(|total| total + len);
+        }
+    }
+
+    let chunksize = config.chunksize_for(total);
+    let mut hasher = match total {
+        Some(len) => S3Etagger::<Md5>::for_known_size(len, config.threshold(), chunksize),
+        None => config.streaming_etagger(chunksize),
+    };
+    let mut n_bytes = 0;
+    for filename in filenames {
+        let result = open_input(filename).and_then(|input| {
+            if output_key.is_some() && inode_key(&input) == output_key {
+                return Err(io::Error::other("is the output file"));
+            }
+            let len = match input {
+                Input::File(file) => {
+                    let size = file.metadata()?.len();
+                    let mut reader = progress::Reader::new(
+                        tee::Reader::new(
+                            retry::Reader::new(file, filename, config.retries),
+                            config.tee.as_ref(),
+                        ),
+                        filename,
+                        Some(size),
+                        config.progress,
+                    );
+                    let len = read_all(&mut reader, buffer, |data| hasher.update(data))?;
+                    config.check_size(filename, size, len)?;
+                    len
+                }
+                Input::Stdin => {
+                    let mut reader = progress::Reader::new(
+                        tee::Reader::new(io::stdin().lock(), config.tee.as_ref()),
+                        filename,
+                        config.stdin_size,
+                        config.progress,
+                    );
+                    let len = read_all(&mut reader, buffer, |data| hasher.update(data))?;
+                    config.check_stdin_size(filename, len)?;
+                    len
+                }
+            };
+            Ok(len)
+        });
+        n_bytes += result.map_err(|e| (filename.as_path(), e))?;
+    }
+    Ok((hasher.finalize(), n_bytes))
+}
+
+/// Prints the parts into which a file would be split for upload, computed from the file size.
+fn explain_file(
+    filename: &path::Path,
+    config: &Config,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let size = if filename.as_os_str() == "-" {
+        config
+            .stdin_size
+            .ok_or_else(|| io::Error::other("size of the standard input is unknown"))?
+    } else {
+        let metadata = fs::metadata(filename)?;
+        if metadata.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "is a directory",
+            ));
+        }
+        metadata.len()
+    };
+    let chunksize = config.chunksize_for(Some(size));
+    let plan = s3etag::chunk_plan(size, config.threshold(), chunksize);
+    output::write_filename(writer, filename)?;
+    match plan.part_count() {
+        Some(n) => {
+            let plural = if n.get() == 1 { "" } else { "s" };
+            writeln!(
+                writer,
+                ": {} part{} of up to {} bytes",
+                n, plural, chunksize
+            )?;
+            for (i, (offset, len)) in plan.parts.iter().enumerate() {
+                writeln!(
+                    writer,
+                    "  part {}: offset {}, length {}",
+                    i + 1,
+                    offset,
+                    len
+                )?;
+            }
+            if n.get() > s3etag::MAX_PART_COUNT {
+                eprintln!(
+                    "warning: {}: more than {} parts, which S3 does not allow; awscli would use a \
+                    larger chunksize",
+                    filename.display(),
+                    s3etag::MAX_PART_COUNT
+                );
+            }
+        }
+        None => writeln!(writer, ": single part of {} bytes", size)?,
+    }
+    Ok(())
+}
+
+/// Computes the ETag for a regular file, selecting the mode from the file size, and returns it
+/// with the number of bytes hashed.
+///
+/// The file size is the logical length reported by the metadata, so the holes of a sparse file
+/// are read and hashed as zeros up to the logical end of the file, and an empty file is hashed by
+/// the read loop as it cannot be mapped.
+fn hash_file(
+    mut file: fs::File,
+    filename: &path::Path,
+    config: &Config,
+    buffer: &mut [u8],
+) -> io::Result<(ETag, u64)> {
+    fn compute_etag_mmap(map: &[u8], config: &Config) -> ETag {
+        let len = map.len() as u64;
+        let chunksize = config.chunksize_for(Some(len));
+
+        #[cfg(feature = "rayon")]
+        return s3etag::etag_parallel::<Md5>(map, config.threshold(), chunksize);
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut hasher = S3Etagger::<Md5>::for_known_size(len, config.threshold(), chunksize);
+            hasher.update(map);
+            hasher.finalize()
+        }
+    }
+
+    let len = file.metadata()?.len();
+
+    if config.mmap {
+        if let Some(map) = map_file(&file, filename) {
+            config.check_size(filename, len, map.len() as u64)?;
+            return Ok((compute_etag_mmap(&map, config), map.len() as u64));
+        }
+    }
+
+    let chunksize = config.chunksize_for(Some(len));
+    let reader = interrupt::Reader::new(progress::Reader::new(
+        tee::Reader::new(
+            retry::Reader::new(&mut file, filename, config.retries),
+            config.tee.as_ref(),
+        ),
+        filename,
+        Some(len),
+        config.progress,
+    ));
+    let (etag, n_bytes) =
+        s3etag::etag_from_read::<Md5>(reader, len, config.threshold(), chunksize, buffer)?;
+    config.check_size(filename, len, n_bytes)?;
+    Ok((etag, n_bytes))
+}
+
+/// Maps a file into memory, or returns `None` with a warning if the file cannot be mapped.
+fn map_file(file: &fs::File, filename: &path::Path) -> Option<memmap2::Mmap> {
+    // Zero-length files cannot be mapped and are processed by the read loop.
+    if file.metadata().is_ok_and(|meta| meta.len() == 0) {
+        return None;
+    }
+
+    // SAFETY: the mapped region may change or even become inaccessible (resulting in `SIGBUS`) if
+    // the file is modified or truncated by another process while being hashed; this risk is
+    // accepted as the user opted in with `--mmap`.
+    match unsafe { memmap2::Mmap::map(file) } {
+        Ok(map) => {
+            #[cfg(unix)]
+            let _ = map.advise(memmap2::Advice::Sequential);
+            Some(map)
+        }
+        Err(e) => {
+            eprintln!(
+                "warning: {}: falling back to read(2) as mmap failed: {}",
+                filename.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Feeds the entire content of a reader to a hasher and returns the result with the number of
+/// bytes read.
+fn compute_etag(
+    mut hasher: impl ETagHasher,
+    reader: &mut impl io::Read,
+    buffer: &mut [u8],
+) -> io::Result<(ETag, u64)> {
+    let len = read_all(reader, buffer, |data| hasher.update(data))?;
+    Ok((hasher.finalize(), len))
+}
+
+/// Feeds the entire content of a reader to `update` and returns the number of bytes read.
+fn read_all(
+    reader: &mut impl io::Read,
+    buffer: &mut [u8],
+    mut update: impl FnMut(&[u8]),
+) -> io::Result<u64> {
+    let mut len = 0;
+    loop {
+        interrupt::check()?;
+        match reader.read(buffer) {
+            Ok(0) => break Ok(len),
+            Ok(n) => {
+                update(&buffer[..n]);
+                len += n as u64;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => break Err(e),
+        }
+    }
 }
 
-#[cfg(feature = "openssl")]
+#[cfg(all(feature = "openssl", not(feature = "md5-asm")))]
 use s3etag::OpensslMd5 as Md5;
 
-#[cfg(not(feature = "openssl"))]
+#[cfg(any(not(feature = "openssl"), feature = "md5-asm"))]
 use md5::Md5; // Either `openssl` or `md-5` must be enabled.
@@ -0,0 +1,293 @@
+//! Output formats of the computed ETags.
+
+use std::{fmt, io, path};
+
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt as _;
+
+use s3etag::ETag;
+
+use crate::{Digest, Md5};
+
+/// The minimum width of the ETag column in the text format.
+///
+/// This width fits any ETag of up to 999,999 parts, well above the limit of 10,000 parts of S3.
+/// Since records are printed as soon as each file is hashed, longer values such as base64 checksums
+/// are followed by a single space and break the alignment; `--tab` avoids the padding altogether.
+pub const ETAG_WIDTH: usize = 39;
+
+/// The minimum width of the size column in the text format, which fits the maximum object size of
+/// 5 TiB.
+pub const SIZE_WIDTH: usize = 13;
+
+/// The minimum width of the base64 column printed after the hexadecimal ETag column, which fits
+/// the base64 form of any ETag of up to 99,999 parts and thus of any ETag of S3.
+pub const BASE64_WIDTH: usize = 30;
+
+/// The tag that precedes ETags in the BSD-style text format.
+pub const ETAG_TAG: &str = "S3ETAG";
+
+/// The output format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Two-column text of the ETag and the filename.
+    Text,
+    /// A JSON array of objects.
+    Json,
+    /// JSON objects in separate lines, which are flushed one by one by a line-buffered writer.
+    JsonLines,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 3] = ["text", "json", "jsonl"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// Options that control the output.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub format: Format,
+    /// Prints only the ETag without padding or the filename in the text format.
+    pub etag_only: bool,
+    /// Terminates each text record with NUL instead of newline.
+    pub zero_terminated: bool,
+    /// Prints ETags in base64 instead of hexadecimal digits.
+    pub base64: bool,
+    /// Reports an error instead of printing a multipart ETag, which cannot be a `Content-MD5`
+    /// header value.
+    pub content_md5: bool,
+    /// Prints the base64 form of each ETag in a column after the hexadecimal one in the text
+    /// format.
+    pub both: bool,
+    /// Prints text records in the BSD-style `TAG (FILE) = ETAG` layout with this tag.
+    pub tag: Option<&'static str>,
+    /// Separates the columns of text records with a tab instead of padding spaces.
+    pub tab: bool,
+    /// Prints the size between the ETag and the filename in the text format.
+    pub size: bool,
+    /// Prints nothing but reports an error for each ETag that does not equal this value.
+    pub expect: Option<ETag>,
+    /// Prints only the value combined from all the ETags by [`s3etag::combine_etags`] at the end.
+    pub combine: bool,
+}
+
+/// A writer that prints ETag records in the specified format.
+#[derive(Debug)]
+pub struct Printer<W> {
+    writer: W,
+    options: Options,
+    n_records: usize,
+    combined: Option<Vec<ETag>>,
+}
+
+impl<W: io::Write> Printer<W> {
+    pub fn new(writer: W, options: Options) -> Self {
+        Self {
+            combined: options.combine.then(Vec::new),
+            writer,
+            options,
+            n_records: 0,
+        }
+    }
+
+    /// Prints a line of text, such as a header, which is not a record.
+    pub fn print_line(&mut self, line: &impl fmt::Display) -> io::Result<()> {
+        write!(self.writer, "{}", line)?;
+        self.writer.write_all(if self.options.zero_terminated {
+            b"\0"
+        } else {
+            b"\n"
+        })
+    }
+
+    /// Prints a record for a file.
+    pub fn print(&mut self, filename: &path::Path, digest: &Digest, size: u64) -> io::Result<()> {
+        if let Some(expected) = self.options.expect {
+            return if *digest == Digest::ETag(expected) {
+                Ok(())
+            } else {
+                Err(io::Error::other(format!(
+                    "ETag mismatch: expected {}, computed {}",
+                    expected, digest
+                )))
+            };
+        }
+
+        if let Some(combined) = &mut self.combined {
+            let Digest::ETag(etag) = digest else {
+                unreachable!("checksums are never combined");
+            };
+            combined.push(*etag);
+            return Ok(());
+        }
+
+        if self.options.content_md5 && digest.part_count().is_some() {
+            return Err(io::Error::other(
+                "Content-MD5 is not applicable to a multipart upload",
+            ));
+        }
+
+        let base64;
+        let value: &dyn fmt::Display = match digest {
+            Digest::ETag(etag) if self.options.base64 => {
+                base64 = etag.to_base64();
+                &base64
+            }
+            _ => digest,
+        };
+        let both = match digest {
+            Digest::ETag(etag) if self.options.both => Some(etag.to_base64()),
+            _ => None,
+        };
+
+        let w = &mut self.writer;
+        match self.options.format {
+            Format::Text if self.options.etag_only => {
+                write!(w, "{}", value)?;
+                if let Some(base64) = &both {
+                    write!(w, " {}", base64)?;
+                }
+                w.write_all(if self.options.zero_terminated {
+                    b"\0"
+                } else {
+                    b"\n"
+                })?;
+            }
+            Format::Text => {
+                match self.options.tag {
+                    Some(tag) => {
+                        write!(w, "{} (", tag)?;
+                        write_filename(w, filename)?;
+                        write!(w, ") = {}", value)?;
+                    }
+                    None if self.options.tab => {
+                        write!(w, "{}\t", value)?;
+                        if let Some(base64) = &both {
+                            write!(w, "{}\t", base64)?;
+                        }
+                        if self.options.size {
+                            write!(w, "{}\t", size)?;
+                        }
+                        write_filename(w, filename)?;
+                    }
+                    None => {
+                        write!(w, "{:<width$} ", value, width = ETAG_WIDTH)?;
+                        if let Some(base64) = &both {
+                            write!(w, "{:<width$} ", base64, width = BASE64_WIDTH)?;
+                        }
+                        if self.options.size {
+                            write!(w, "{:>width$} ", size, width = SIZE_WIDTH)?;
+                        }
+                        write_filename(w, filename)?;
+                    }
+                }
+                w.write_all(if self.options.zero_terminated {
+                    b"\0"
+                } else {
+                    b"\n"
+                })?;
+            }
+            Format::Json => {
+                w.write_all(if self.n_records == 0 { b"[\n" } else { b",\n" })?;
+                w.write_all(b"  ")?;
+                write_json_object(w, filename, digest, value, size)?;
+            }
+            Format::JsonLines => {
+                write_json_object(w, filename, digest, value, size)?;
+                w.write_all(b"\n")?;
+            }
+        }
+        self.n_records += 1;
+        Ok(())
+    }
+
+    /// Drops the ETags collected to be combined so that [`Printer::finish`] does not print the
+    /// combined value.
+    pub fn discard_combined(&mut self) {
+        self.combined = None;
+    }
+
+    /// Writes the trailer of the output, if any, and flushes the writer.
+    ///
+    /// The combined value is written here if `combine` is set.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(combined) = self.combined.take() {
+            write!(self.writer, "{}", s3etag::combine_etags::<Md5>(combined))?;
+            self.writer.write_all(if self.options.zero_terminated {
+                b"\0"
+            } else {
+                b"\n"
+            })?;
+        }
+        if self.options.format == Format::Json {
+            let trailer: &[u8] = if self.n_records == 0 {
+                b"[]\n"
+            } else {
+                b"\n]\n"
+            };
+            self.writer.write_all(trailer)?;
+        }
+        self.writer.flush()
+    }
+}
+
+/// Writes a filename as-is if possible.
+pub fn write_filename(w: &mut impl io::Write, filename: &path::Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return w.write_all(filename.as_os_str().as_bytes());
+    #[cfg(not(unix))]
+    return write!(w, "{}", filename.display());
+}
+
+/// Writes a JSON object describing a file.
+///
+/// The filename is converted lossily, replacing invalid UTF-8 sequences with `U+FFFD`, because
+/// JSON strings cannot represent arbitrary bytes. The formatted `value` of `digest` is written
+/// under the `etag` key, or under the `checksum` key for an additional checksum.
+fn write_json_object(
+    w: &mut impl io::Write,
+    filename: &path::Path,
+    digest: &Digest,
+    value: &dyn fmt::Display,
+    size: u64,
+) -> io::Result<()> {
+    let key = match digest {
+        Digest::ETag(_) => "etag",
+        Digest::Checksum(_) => "checksum",
+    };
+    w.write_all(b"{\"file\": ")?;
+    write_json_string(w, &filename.to_string_lossy())?;
+    write!(w, ", \"{key}\": \"{value}\", \"size\": {size}, \"parts\": ")?;
+    match digest.part_count() {
+        Some(n) => write!(w, "{}}}", n),
+        None => w.write_all(b"null}"),
+    }
+}
+
+/// Writes a string as a JSON string literal.
+fn write_json_string(w: &mut impl io::Write, s: &str) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    let mut rest = s;
+    while let Some(pos) = rest.find(|c: char| c == '"' || c == '\\' || c.is_ascii_control()) {
+        w.write_all(&rest.as_bytes()[..pos])?;
+        match rest.as_bytes()[pos] {
+            b'"' => w.write_all(b"\\\"")?,
+            b'\\' => w.write_all(b"\\\\")?,
+            b'\n' => w.write_all(b"\\n")?,
+            b'\r' => w.write_all(b"\\r")?,
+            b'\t' => w.write_all(b"\\t")?,
+            c => write!(w, "\\u{:04x}", c)?,
+        }
+        rest = &rest[pos + 1..];
+    }
+    w.write_all(rest.as_bytes())?;
+    w.write_all(b"\"")
+}
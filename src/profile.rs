@@ -0,0 +1,135 @@
+//! Multipart settings read from the shared AWS config file.
+
+use std::{env, fs, io, path};
+
+/// The `s3` transfer settings of a profile that affect ETags.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Settings {
+    pub multipart_threshold: Option<String>,
+    pub multipart_chunksize: Option<String>,
+}
+
+/// Returns the path to the config file, which is `AWS_CONFIG_FILE` or `~/.aws/config`.
+pub fn config_path() -> Option<path::PathBuf> {
+    if let Some(path) = env::var_os("AWS_CONFIG_FILE") {
+        return Some(path.into());
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(path::Path::new(&home).join(".aws").join("config"))
+}
+
+/// Reads the settings of a profile from the config file at `path`.
+///
+/// This function returns empty settings if the file does not exist.
+pub fn load(path: &path::Path, profile: &str) -> io::Result<Settings> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(parse(&content, profile)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses the settings of a profile in the INI-like format of the config file.
+///
+/// The settings are looked up in the nested `s3` section of `[default]` for the `default` profile
+/// and `[profile NAME]` for the others, as awscli does.
+fn parse(content: &str, profile: &str) -> Settings {
+    let mut settings = Settings::default();
+    let mut in_profile = false;
+    let mut in_s3 = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let name = name.trim();
+            in_profile = match name.strip_prefix("profile ") {
+                Some(name) => name.trim() == profile,
+                None => name == "default" && profile == "default",
+            };
+            in_s3 = false;
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if !line.starts_with([' ', '\t']) {
+            // A top-level key ends the nested section.
+            in_s3 = in_profile && key == "s3" && value.is_empty();
+        } else if in_s3 {
+            match key {
+                "multipart_threshold" => settings.multipart_threshold = Some(value.to_owned()),
+                "multipart_chunksize" => settings.multipart_chunksize = Some(value.to_owned()),
+                _ => (),
+            }
+        }
+    }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# comment
+[default]
+region = us-east-1
+s3 =
+  multipart_threshold = 16mb
+  ; comment
+  multipart_chunksize = 1Gib
+
+[profile dev]
+s3 =
+    max_concurrent_requests = 20
+    multipart_chunksize = 5Kb
+output = json
+  multipart_threshold = 1MB
+
+[profile  other ]
+multipart_threshold = 1MB
+s3 =
+\tmultipart_threshold = 64MB
+";
+
+    fn settings(threshold: Option<&str>, chunksize: Option<&str>) -> Settings {
+        Settings {
+            multipart_threshold: threshold.map(str::to_owned),
+            multipart_chunksize: chunksize.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(
+            parse(CONFIG, "default"),
+            settings(Some("16mb"), Some("1Gib"))
+        );
+        // The nested section ends at the next top-level key.
+        assert_eq!(parse(CONFIG, "dev"), settings(None, Some("5Kb")));
+        // Top-level keys outside the nested section are ignored.
+        assert_eq!(parse(CONFIG, "other"), settings(Some("64MB"), None));
+        assert_eq!(parse(CONFIG, "missing"), Settings::default());
+        assert_eq!(
+            parse("[prod]\ns3 =\n  multipart_threshold = 1MB\n", "prod"),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn sizes_in_any_case() {
+        let parse_size = |s: Option<String>| s3etag::parse_size(&s.unwrap()).unwrap();
+        let default = parse(CONFIG, "default");
+        assert_eq!(parse_size(default.multipart_threshold), 16 << 20);
+        assert_eq!(parse_size(default.multipart_chunksize), 1 << 30);
+        assert_eq!(
+            parse_size(parse(CONFIG, "dev").multipart_chunksize),
+            5 << 10
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Progress display of long-running hashing.
+
+use std::{io, path};
+
+/// A reader adapter that renders the progress of reading a file on the standard error.
+///
+/// The progress is shown in percent if the total size is known or in MiB otherwise, and it is
+/// rendered only when the displayed value changes. The line is cleared when the reader is dropped.
+#[derive(Debug)]
+pub struct Reader<'a, R> {
+    inner: R,
+    filename: &'a path::Path,
+    total: Option<u64>,
+    processed: u64,
+    /// The last rendered value, or `None` if nothing has been rendered.
+    last: Option<u64>,
+    enabled: bool,
+}
+
+impl<'a, R> Reader<'a, R> {
+    /// Creates a new adapter, which renders nothing unless `enabled` is set.
+    pub fn new(inner: R, filename: &'a path::Path, total: Option<u64>, enabled: bool) -> Self {
+        Self {
+            inner,
+            filename,
+            total,
+            processed: 0,
+            last: None,
+            enabled,
+        }
+    }
+
+    fn render(&mut self) {
+        let value = match self.total {
+            Some(total) if total > 0 => self.processed * 100 / total,
+            _ => self.processed >> 20,
+        };
+        if self.last != Some(value) {
+            self.last = Some(value);
+            match self.total {
+                Some(_) => eprint!("\r\x1b[2K{}: {:>3}%", self.filename.display(), value),
+                None => eprint!("\r\x1b[2K{}: {} MiB", self.filename.display(), value),
+            }
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.processed += n as u64;
+        if self.enabled {
+            self.render();
+        }
+        Ok(n)
+    }
+}
+
+impl<R> Drop for Reader<'_, R> {
+    fn drop(&mut self) {
+        if self.last.is_some() {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Retrying of transient read errors, such as those on network filesystems.
+
+use std::{io, path, thread, time};
+
+/// The delay before the first retry, which doubles for each consecutive retry.
+const INITIAL_DELAY: time::Duration = time::Duration::from_millis(100);
+
+/// The upper limit of the delay between retries.
+const MAX_DELAY: time::Duration = time::Duration::from_secs(30);
+
+/// A reader adapter that retries a read that fails with a transient error up to `retries` times,
+/// sleeping for an exponentially increasing delay before each retry.
+///
+/// The inner reader is expected to be positioned at the start of the file. The adapter tracks the
+/// offset of the bytes it has returned and seeks back to it before retrying, since the file
+/// position is unspecified after a failed read. The count of retries is reset by every successful
+/// read, so `retries` limits the consecutive failures rather than the total.
+#[derive(Debug)]
+pub struct Reader<'a, R> {
+    inner: R,
+    filename: &'a path::Path,
+    retries: u32,
+    offset: u64,
+}
+
+impl<'a, R> Reader<'a, R> {
+    /// Creates a new adapter, which retries nothing if `retries` is zero.
+    pub fn new(inner: R, filename: &'a path::Path, retries: u32) -> Self {
+        Self {
+            inner,
+            filename,
+            retries,
+            offset: 0,
+        }
+    }
+}
+
+impl<R: io::Read + io::Seek> io::Read for Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.read(buf) {
+                Ok(n) => {
+                    self.offset += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    let delay = INITIAL_DELAY
+                        .saturating_mul(1 << attempt.min(16))
+                        .min(MAX_DELAY);
+                    attempt += 1;
+                    eprintln!(
+                        "warning: {}: retrying in {:?} ({}/{}) after read error: {}",
+                        self.filename.display(),
+                        delay,
+                        attempt,
+                        self.retries,
+                        e
+                    );
+                    thread::sleep(delay);
+                    crate::interrupt::check()?;
+                    self.inner.seek(io::SeekFrom::Start(self.offset))?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Returns true if an error is considered transient and worth retrying.
+///
+/// `EIO` and timeouts (`ETIMEDOUT` on Unix) are considered transient, as they are typically
+/// reported by network filesystems such as NFS on temporary server or network failures. Other
+/// errors, such as a stale file handle, persist until the file is reopened, and `Interrupted` is
+/// retried immediately by the read loop regardless of this adapter.
+fn is_transient(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(nix::errno::Errno::EIO as i32) {
+        return true;
+    }
+    e.kind() == io::ErrorKind::TimedOut
+}
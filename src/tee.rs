@@ -0,0 +1,62 @@
+//! Copying of the input to a file while it is being hashed.
+
+use std::{fs, io, path, sync};
+
+/// A destination file that receives a copy of the bytes read.
+#[derive(Debug)]
+pub struct Dest {
+    path: path::PathBuf,
+    writer: sync::Mutex<io::BufWriter<fs::File>>,
+}
+
+impl Dest {
+    /// Creates or truncates the destination file.
+    pub fn create(path: &path::Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_owned(),
+            writer: sync::Mutex::new(io::BufWriter::new(fs::File::create(path)?)),
+        })
+    }
+
+    /// Returns the path to the destination file.
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    /// Flushes the buffered bytes to the destination file.
+    pub fn finish(&self) -> io::Result<()> {
+        io::Write::flush(&mut *self.writer.lock().unwrap())
+    }
+
+    /// Writes bytes to the destination, annotating an error with the destination path so that it
+    /// is distinguished from errors reading the source.
+    fn write_all(&self, data: &[u8]) -> io::Result<()> {
+        io::Write::write_all(&mut *self.writer.lock().unwrap(), data).map_err(|e| {
+            io::Error::other(format!("cannot write to {}: {}", self.path.display(), e))
+        })
+    }
+}
+
+/// A reader adapter that copies the bytes read to a destination, if any.
+#[derive(Debug)]
+pub struct Reader<'a, R> {
+    inner: R,
+    dest: Option<&'a Dest>,
+}
+
+impl<'a, R> Reader<'a, R> {
+    /// Creates a new adapter, which copies nothing if `dest` is `None`.
+    pub fn new(inner: R, dest: Option<&'a Dest>) -> Self {
+        Self { inner, dest }
+    }
+}
+
+impl<R: io::Read> io::Read for Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(dest) = self.dest {
+            dest.write_all(&buf[..n])?;
+        }
+        Ok(n)
+    }
+}
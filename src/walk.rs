@@ -0,0 +1,191 @@
+//! Expansion of FILE arguments with wildcards and in the recursive mode.
+
+use std::{fs, io, path, vec};
+
+/// A path to be processed or an error with the offending path.
+pub type Entry = Result<path::PathBuf, (path::PathBuf, io::Error)>;
+
+fn entry_path(entry: &Entry) -> &path::Path {
+    match entry {
+        Ok(path) | Err((path, _)) => path,
+    }
+}
+
+/// An iterator over FILE arguments that descends into directories if configured so.
+///
+/// A FILE argument containing wildcard characters (`*`, `?`, or `[`) is expanded to the sorted list
+/// of matching paths unless it names an existing file, so that patterns work even where the shell
+/// does not expand them. A pattern that matches nothing results in an error. Directory entries are
+/// visited depth-first in the sorted order of their file names, skipping those that match any of
+/// the exclusion patterns. This iterator yields regular files and any other non-directory FILE
+/// arguments as-is, and it yields an error with the offending path if a directory or its entry
+/// cannot be inspected, without aborting the whole walk.
+#[derive(Debug)]
+pub struct FileArgs<I> {
+    args: I,
+    expanded: vec::IntoIter<Entry>,
+    stack: Vec<Dir>,
+    /// The directory FILE argument being walked, to which excluded paths are relative.
+    root: path::PathBuf,
+    recursive: bool,
+    follow_symlinks: bool,
+    excludes: Vec<glob::Pattern>,
+}
+
+/// A directory being walked.
+#[derive(Debug)]
+struct Dir {
+    entries: vec::IntoIter<path::PathBuf>,
+    /// The canonical path used to detect symbolic link loops (only set when following links).
+    real_path: Option<path::PathBuf>,
+}
+
+impl<I> FileArgs<I> {
+    /// Creates a new iterator over `args`.
+    ///
+    /// Symbolic links found in the walk are skipped unless `follow_symlinks` is set, whereas
+    /// those given as FILE arguments are always followed.
+    ///
+    /// A file or directory found in the walk is skipped if it matches any of `excludes`. A pattern
+    /// without `/` is matched against the file name at any depth, and a pattern with `/` is
+    /// matched against the path relative to the directory FILE argument, where `*` does not match
+    /// `/` but `**` matches any number of directories. FILE arguments themselves are never
+    /// excluded.
+    pub fn new(
+        args: I,
+        recursive: bool,
+        follow_symlinks: bool,
+        excludes: Vec<glob::Pattern>,
+    ) -> Self {
+        Self {
+            args,
+            expanded: Vec::new().into_iter(),
+            stack: Vec::new(),
+            root: path::PathBuf::new(),
+            recursive,
+            follow_symlinks,
+            excludes,
+        }
+    }
+
+    /// Returns `true` if a path found in the walk matches any of the exclusion patterns.
+    fn is_excluded(&self, path: &path::Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let name = path.file_name().map(path::Path::new);
+        self.excludes.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_path_with(relative, options)
+            } else {
+                name.is_some_and(|name| pattern.matches_path_with(name, options))
+            }
+        })
+    }
+
+    /// Reads directory entries and pushes them onto the stack.
+    fn push_dir(&mut self, dir: &path::Path) -> io::Result<()> {
+        let real_path = if self.follow_symlinks {
+            let real_path = fs::canonicalize(dir)?;
+            if self
+                .stack
+                .iter()
+                .any(|e| e.real_path.as_ref() == Some(&real_path))
+            {
+                return Err(io::Error::other("file system loop detected"));
+            }
+            Some(real_path)
+        } else {
+            None
+        };
+
+        let mut entries = fs::read_dir(dir)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        self.stack.push(Dir {
+            entries: entries.into_iter(),
+            real_path,
+        });
+        Ok(())
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a path::PathBuf>> FileArgs<I> {
+    /// Returns the next FILE argument, expanding it if it is an unmatched wildcard pattern.
+    fn next_arg(&mut self) -> Option<Entry> {
+        if let Some(entry) = self.expanded.next() {
+            return Some(entry);
+        }
+
+        let arg = self.args.next()?;
+        let pattern = match arg.to_str() {
+            Some(s) if s.contains(['*', '?', '[']) && fs::symlink_metadata(arg).is_err() => s,
+            _ => return Some(Ok(arg.clone())),
+        };
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..Default::default()
+        };
+        let mut expanded = match glob::glob_with(pattern, options) {
+            Ok(paths) => paths
+                .map(|e| e.map_err(|e| (e.path().to_owned(), e.into())))
+                .collect::<Vec<_>>(),
+            Err(e) => return Some(Err((arg.clone(), io::Error::other(e.msg)))),
+        };
+        if expanded.is_empty() {
+            return Some(Err((arg.clone(), io::Error::other("no matches found"))));
+        }
+        expanded.sort_by(|a, b| entry_path(a).cmp(entry_path(b)));
+        self.expanded = expanded.into_iter();
+        self.expanded.next()
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a path::PathBuf>> Iterator for FileArgs<I> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(dir) = self.stack.last_mut() else {
+                let arg = match self.next_arg()? {
+                    Ok(arg) => arg,
+                    Err(e) => return Some(Err(e)),
+                };
+                if self.recursive && arg.as_os_str() != "-" && arg.is_dir() {
+                    if let Err(e) = self.push_dir(&arg) {
+                        return Some(Err((arg, e)));
+                    }
+                    self.root = arg;
+                    continue;
+                }
+                return Some(Ok(arg));
+            };
+
+            let Some(path) = dir.entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if self.is_excluded(&path) {
+                continue;
+            }
+            let result_meta = if self.follow_symlinks {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            match result_meta {
+                Ok(meta) if meta.is_dir() => {
+                    if let Err(e) = self.push_dir(&path) {
+                        return Some(Err((path, e)));
+                    }
+                }
+                Ok(meta) if meta.is_file() => return Some(Ok(path)),
+                Ok(_) => (), // skip symbolic links not followed and special files
+                Err(e) => return Some(Err((path, e))),
+            }
+        }
+    }
+}
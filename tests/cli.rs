@@ -0,0 +1,179 @@
+//! Tests of the command-line tool run as a separate process.
+
+#![cfg(feature = "cli")]
+
+use std::{env, fs, path, process};
+
+/// Creates an empty directory for a test.
+fn test_dir(name: &str) -> path::PathBuf {
+    let dir = env::temp_dir().join(format!("s3etag-cli-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the tool with arguments in a directory.
+fn s3etag(dir: &path::Path, args: &[&str]) -> process::Output {
+    process::Command::new(env!("CARGO_BIN_EXE_s3etag"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn tee_to_input_file() {
+    let dir = test_dir("tee");
+    fs::write(dir.join("x"), b"123456789").unwrap();
+
+    let out = s3etag(&dir, &["--tee", "x", "x"]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+    assert_eq!(fs::read(dir.join("x")).unwrap(), b"123456789");
+
+    let out = s3etag(&dir, &["--tee", "y", "x"]);
+    assert!(out.status.success());
+    assert_eq!(fs::read(dir.join("y")).unwrap(), b"123456789");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_to_input_file() {
+    let dir = test_dir("output");
+    fs::write(dir.join("keep"), b"123456789").unwrap();
+
+    let out = s3etag(&dir, &["--output", "keep", "keep"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("output file is also an input FILE"));
+    assert_eq!(fs::read(dir.join("keep")).unwrap(), b"123456789");
+
+    // The output file found in a directory is skipped, while the other files are hashed.
+    fs::write(dir.join("a"), b"123456789").unwrap();
+    let out = s3etag(&dir, &["--output", "keep", "-r", "."]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("skipping the output file"));
+    let records = fs::read_to_string(dir.join("keep")).unwrap();
+    assert_eq!(records, "25f9e794323b453885f5181f1b624d0b        ./a\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unknown_algorithm() {
+    let dir = test_dir("algorithm");
+    let out = s3etag(&dir, &["--algorithm", "bogus", "x"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("unknown algorithm `bogus` (expected one of: md5"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn check_tag_output() {
+    let dir = test_dir("tag");
+    fs::write(dir.join("a) = b"), b"123456789").unwrap();
+
+    let out = s3etag(&dir, &["--tag", "a) = b"]);
+    assert!(out.status.success());
+    assert_eq!(
+        out.stdout,
+        b"S3ETAG (a) = b) = 25f9e794323b453885f5181f1b624d0b\n"
+    );
+    fs::write(dir.join("manifest"), &out.stdout).unwrap();
+
+    let out = s3etag(&dir, &["--check", "manifest"]);
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"a) = b: OK\n");
+
+    fs::write(dir.join("a) = b"), b"12345678").unwrap();
+    let out = s3etag(&dir, &["--check", "manifest"]);
+    assert!(!out.status.success());
+    assert_eq!(out.stdout, b"a) = b: FAILED\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn check_header() {
+    let dir = test_dir("header");
+    fs::write(dir.join("a"), b"123456789").unwrap();
+
+    let args = ["--header", "--threshold", "4", "--chunksize", "4", "a"];
+    let out = s3etag(&dir, &args);
+    assert!(out.status.success());
+    let manifest = String::from_utf8(out.stdout).unwrap();
+    let (header, record) = manifest.split_once('\n').unwrap();
+    assert!(header.starts_with("# s3etag "));
+    assert!(header.ends_with(" threshold=4 chunksize=4 auto-chunksize=false algorithm=md5"));
+    assert_eq!(record, "393e928fcf5925fcbd3a06aaf20b2d38-3      a\n");
+    fs::write(dir.join("manifest"), &manifest).unwrap();
+    fs::write(dir.join("no-header"), record).unwrap();
+
+    // The header takes precedence over the command-line options, which disagree with it.
+    for args in [
+        &["--check", "manifest"][..],
+        &[
+            "--check",
+            "manifest",
+            "--threshold",
+            "8MB",
+            "--chunksize",
+            "8MB",
+        ],
+    ] {
+        let out = s3etag(&dir, args);
+        assert!(out.status.success(), "{:?}", args);
+        assert_eq!(out.stdout, b"a: OK\n");
+    }
+
+    let out = s3etag(&dir, &["--check", "no-header"]);
+    assert!(!out.status.success());
+    assert_eq!(out.stdout, b"a: FAILED\n");
+
+    // An invalid header fails the check but the following lines are still checked.
+    let invalid = manifest.replacen("threshold=4 ", "", 1);
+    fs::write(dir.join("invalid"), invalid).unwrap();
+    let out = s3etag(&dir, &["--check", "invalid"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid header: missing threshold"));
+    assert_eq!(out.stdout, b"a: FAILED\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tab_output() {
+    let dir = test_dir("tab");
+    fs::write(dir.join(" a"), b"123456789").unwrap();
+
+    let out = s3etag(&dir, &["--tab", "-z", " a"]);
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"25f9e794323b453885f5181f1b624d0b\t a\0");
+
+    let out = s3etag(&dir, &["--tab", " a"]);
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"25f9e794323b453885f5181f1b624d0b\t a\n");
+    fs::write(dir.join("manifest"), &out.stdout).unwrap();
+    let out = s3etag(&dir, &["--check", "manifest"]);
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b" a: OK\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn match_etag_small_chunksize() {
+    let dir = test_dir("match");
+    fs::write(dir.join("a"), b"123456789").unwrap();
+
+    let out = s3etag(
+        &dir,
+        &["--match-etag", "393e928fcf5925fcbd3a06aaf20b2d38-3", "a"],
+    );
+    assert!(out.status.success());
+    assert_eq!(out.stdout, format!("{:<39} a\n", 4).into_bytes());
+
+    let out = s3etag(
+        &dir,
+        &["--match-etag", "f452076d11f30dfc8658282bf01f27f4-3", "a"],
+    );
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("candidate chunksizes (3, 4)"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,7 @@
+//! Helpers shared by the integration tests.
+
+/// Returns the content of a fixture, which repeats the bytes from 0 to 250 so that no two chunks
+/// at different offsets are identical.
+pub fn fixture(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
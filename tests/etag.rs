@@ -0,0 +1,208 @@
+//! Known-answer tests of ETags for deterministic fixtures at the boundaries of the modes.
+//!
+//! The expected ETags follow the part splitting of awscli for the given `multipart_threshold` and
+//! `multipart_chunksize` values. The tests run for each MD5 backend enabled, so run
+//! `cargo test --features openssl` to cover `openssl` as well as `md-5`, and
+//! `cargo test --no-default-features --features cli,openssl` to cover `openssl` alone.
+
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::{env, fs, io, process};
+
+use s3etag::{ETag, ETagHasher, Md5Hasher, S3Etagger};
+
+use common::fixture;
+
+const MIB: usize = 1 << 20;
+
+/// A fixture of `len` bytes uploaded with the threshold and chunksize given in MiB.
+struct Case {
+    name: &'static str,
+    len: usize,
+    threshold: u64,
+    chunksize: usize,
+    expected: &'static str,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "empty",
+        len: 0,
+        threshold: 8,
+        chunksize: 8,
+        expected: "d41d8cd98f00b204e9800998ecf8427e",
+    },
+    Case {
+        name: "sub-threshold",
+        len: 8 * MIB - 1,
+        threshold: 8,
+        chunksize: 8,
+        expected: "1acc725c3c7ab01db7fb5ab34863fb9a",
+    },
+    Case {
+        name: "exactly-threshold",
+        len: 8 * MIB,
+        threshold: 8,
+        chunksize: 8,
+        expected: "e039da9ee1fa336ed66c53824ac058a7-1",
+    },
+    Case {
+        name: "chunk-aligned",
+        len: 16 * MIB,
+        threshold: 8,
+        chunksize: 8,
+        expected: "c6f57ab91799c96d38c98664ee613a41-2",
+    },
+    Case {
+        name: "multi-chunk",
+        len: 20 * MIB + 1,
+        threshold: 8,
+        chunksize: 8,
+        expected: "f92841ee8a3cd6a9bc7a7098a68a9940-3",
+    },
+    Case {
+        name: "small-chunksize",
+        len: 8 * MIB,
+        threshold: 8,
+        chunksize: 5,
+        expected: "9796342ec11a0c0f0214916d1215419f-2",
+    },
+    Case {
+        name: "small-chunksize-aligned",
+        len: 15 * MIB,
+        threshold: 8,
+        chunksize: 5,
+        expected: "10c02e8daa40504510dcad6f43626089-3",
+    },
+];
+
+/// Checks the ETag of each case computed from memory in the known-size and streaming modes and
+/// from a file.
+fn check_backend<H: Md5Hasher>(backend: &str) -> io::Result<()> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    for case in CASES {
+        let data = fixture(case.len);
+        let threshold = NonZeroU64::new(case.threshold * MIB as u64).unwrap();
+        let chunksize = NonZeroUsize::new(case.chunksize * MIB).unwrap();
+        let check = |etag: ETag| assert_eq!(etag.to_string(), case.expected, "{}", case.name);
+
+        // Feed the data in pieces that never align with the chunk boundaries.
+        let mut hasher = S3Etagger::<H>::for_known_size(data.len() as u64, threshold, chunksize);
+        data.chunks(MIB - 3).for_each(|piece| hasher.update(piece));
+        check(hasher.finalize());
+
+        let mut hasher = S3Etagger::<H>::new(threshold, chunksize);
+        data.chunks(MIB - 3).for_each(|piece| hasher.update(piece));
+        check(hasher.finalize());
+
+        let path = env::temp_dir().join(format!(
+            "s3etag-test-{}-{}-{}",
+            process::id(),
+            backend,
+            case.name
+        ));
+        fs::write(&path, &data)?;
+        let result = fs::File::open(&path).and_then(|mut file| {
+            Ok(s3etag::etag_from_file::<H>(
+                &mut file,
+                threshold,
+                chunksize,
+                &mut buffer,
+            )?)
+        });
+        fs::remove_file(&path)?;
+        check(result?);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "md-5")]
+#[test]
+fn md5_backend() -> io::Result<()> {
+    check_backend::<md5::Md5>("md-5")
+}
+
+#[cfg(feature = "openssl")]
+#[test]
+fn openssl_backend() -> io::Result<()> {
+    check_backend::<s3etag::OpensslMd5>("openssl")
+}
+
+/// Checks that hashing the parts in parallel keeps them in order for various part counts.
+#[cfg(all(feature = "rayon", feature = "md-5"))]
+#[test]
+fn parallel_matches_serial() {
+    let chunksize = NonZeroUsize::new(1000).unwrap();
+    for threshold in [1, 1000, 4096] {
+        let threshold = NonZeroU64::new(threshold).unwrap();
+        for len in [
+            0, 1, 999, 1000, 1001, 2000, 2999, 7000, 63_999, 64_000, 200_001,
+        ] {
+            let data = fixture(len);
+            assert_eq!(
+                s3etag::etag_parallel::<md5::Md5>(&data, threshold, chunksize),
+                s3etag::etag::<md5::Md5>(&data, threshold, chunksize),
+                "{} bytes with threshold {}",
+                len,
+                threshold
+            );
+        }
+    }
+}
+
+/// Checks `etag_from_file` for an empty file, a file of exactly the threshold, and a sparse file,
+/// whose holes are hashed as zeros.
+#[cfg(feature = "md-5")]
+#[test]
+fn file_edge_cases() -> io::Result<()> {
+    use io::{Seek as _, Write as _};
+
+    let threshold = s3etag::AWSCLI_DEFAULT_THRESHOLD;
+    let chunksize = s3etag::AWSCLI_DEFAULT_CHUNKSIZE;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let path = env::temp_dir().join(format!("s3etag-test-{}-edge", process::id()));
+    let mut etag_of = |file: &mut fs::File| {
+        file.rewind()?;
+        io::Result::Ok(s3etag::etag_from_file::<md5::Md5>(
+            file,
+            threshold,
+            chunksize,
+            &mut buffer,
+        )?)
+    };
+
+    let result = (|| {
+        let mut file = fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        let empty = etag_of(&mut file)?;
+        assert_eq!(empty.to_string(), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(empty.part_count(), None);
+
+        file.write_all(&fixture(8 * MIB))?;
+        let exact = etag_of(&mut file)?;
+        assert_eq!(exact.to_string(), "e039da9ee1fa336ed66c53824ac058a7-1");
+
+        // A hole of 20 MiB followed by data in the third part.
+        file.set_len(0)?;
+        file.seek(io::SeekFrom::Start(20 * MIB as u64))?;
+        file.write_all(b"123456789")?;
+        let mut data = vec![0u8; 20 * MIB];
+        data.extend_from_slice(b"123456789");
+        let sparse = etag_of(&mut file)?;
+        assert_eq!(
+            sparse,
+            s3etag::etag::<md5::Md5>(&data, threshold, chunksize)
+        );
+        assert_eq!(sparse.part_count(), NonZeroUsize::new(3));
+        Ok(())
+    })();
+    fs::remove_file(&path)?;
+    result
+}
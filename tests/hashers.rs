@@ -0,0 +1,102 @@
+//! Tests of the hasher states at mode boundaries, across resets and clones, and for different
+//! slicings of the input.
+
+#![cfg(all(feature = "md-5", feature = "alloc"))]
+
+mod common;
+
+use std::num::{NonZeroU64, NonZeroUsize};
+
+use s3etag::{AdaptiveETagHasher, ETagHasher, ETagHasherMulti, S3Etagger};
+
+use common::fixture;
+
+#[test]
+fn adaptive_at_threshold() {
+    let threshold = NonZeroU64::new(1000).unwrap();
+    let chunksize = NonZeroUsize::new(300).unwrap();
+    for len in [999, 1000, 1001] {
+        let data = fixture(len);
+        let mut hasher = AdaptiveETagHasher::<md5::Md5>::new(threshold, chunksize);
+        data.chunks(7).for_each(|piece| hasher.update(piece));
+        let etag = hasher.finalize();
+
+        let mut expected = S3Etagger::<md5::Md5>::for_known_size(len as u64, threshold, chunksize);
+        expected.update(&data);
+        assert_eq!(etag, expected.finalize(), "{} bytes", len);
+        assert_eq!(etag.part_count().is_some(), len >= 1000, "{} bytes", len);
+    }
+}
+
+#[test]
+fn multi_reused_after_reset() {
+    let chunksize = NonZeroUsize::new(300).unwrap();
+    for (first, second) in [(fixture(1000), fixture(450)), (fixture(450), fixture(1000))] {
+        let mut hasher = ETagHasherMulti::<md5::Md5>::with_part_digests(chunksize);
+        hasher.update(&first);
+        hasher.reset();
+        assert_eq!(hasher.bytes_processed(), 0);
+        assert_eq!(hasher.current_part_index(), 0);
+        hasher.update(&second);
+
+        let mut fresh = ETagHasherMulti::<md5::Md5>::with_part_digests(chunksize);
+        fresh.update(&second);
+        assert_eq!(hasher.finalize_with_parts(), fresh.finalize_with_parts());
+    }
+}
+
+#[test]
+fn multi_cloned_mid_stream() {
+    let chunksize = NonZeroUsize::new(300).unwrap();
+    let (head, tail_a, tail_b) = (fixture(450), fixture(700), fixture(10));
+    let mut hasher = ETagHasherMulti::<md5::Md5>::new(chunksize);
+    hasher.update(&head);
+    let mut fork = hasher.clone();
+    hasher.update(&tail_a);
+    fork.update(&tail_b);
+
+    for (etag, tail) in [(hasher.finalize(), &tail_a), (fork.finalize(), &tail_b)] {
+        let mut expected = ETagHasherMulti::<md5::Md5>::new(chunksize);
+        expected.update(&head);
+        expected.update(tail);
+        assert_eq!(etag, expected.finalize());
+    }
+}
+
+/// Splits data into slices of uneven lengths, some of which cross the chunk boundaries.
+fn uneven_slices(data: &[u8]) -> Vec<&[u8]> {
+    let mut slices = Vec::new();
+    let mut rest = data;
+    for len in [1, 298, 2, 0, 301, 7, 600].into_iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (slice, tail) = rest.split_at(len.min(rest.len()));
+        slices.push(slice);
+        rest = tail;
+    }
+    slices
+}
+
+#[test]
+fn update_from_iter_independent_of_slicing() {
+    let threshold = NonZeroU64::new(500).unwrap();
+    let chunksize = NonZeroUsize::new(300).unwrap();
+    for len in [0, 299, 300, 301, 1000, 2000] {
+        let data = fixture(len);
+        let slices = uneven_slices(&data);
+        assert_eq!(slices.concat(), data);
+
+        let mut whole = ETagHasherMulti::<md5::Md5>::new(chunksize);
+        whole.update_from_iter([&data]);
+        let mut pieces = ETagHasherMulti::<md5::Md5>::new(chunksize);
+        pieces.update_from_iter(&slices);
+        assert_eq!(whole.finalize(), pieces.finalize(), "{} bytes", len);
+
+        let mut whole = S3Etagger::<md5::Md5>::new(threshold, chunksize);
+        whole.update_from_iter([&data]);
+        let mut pieces = S3Etagger::<md5::Md5>::new(threshold, chunksize);
+        pieces.update_from_iter(&slices);
+        assert_eq!(whole.finalize(), pieces.finalize(), "{} bytes", len);
+    }
+}
@@ -0,0 +1,30 @@
+//! Round-trip tests of the serde support of `ETag`.
+
+#![cfg(feature = "serde")]
+
+use s3etag::ETag;
+
+#[test]
+fn round_trip() {
+    for s in [
+        "d41d8cd98f00b204e9800998ecf8427e",
+        "f452076d11f30dfc8658282bf01f27f4-3",
+    ] {
+        let etag: ETag = s.parse().unwrap();
+        let json = serde_json::to_string(&etag).unwrap();
+        assert_eq!(json, format!("\"{}\"", s));
+        assert_eq!(serde_json::from_str::<ETag>(&json).unwrap(), etag);
+    }
+}
+
+#[test]
+fn reject_malformed() {
+    for json in [
+        "\"d41d8cd98f00b204e9800998ecf8427\"",
+        "\"f452076d11f30dfc8658282bf01f27f4-\"",
+        "\"not an etag\"",
+        "42",
+    ] {
+        assert!(serde_json::from_str::<ETag>(json).is_err(), "{}", json);
+    }
+}